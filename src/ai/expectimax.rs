@@ -0,0 +1,139 @@
+//! Depth-limited expectimax search over moves and tile spawns.
+
+use std::collections::HashMap;
+
+use crate::ai::heuristic::{Heuristic, WeightedHeuristic};
+use crate::bitboard::Bitboard;
+use crate::game::{Direction, Game};
+
+/// Chance of spawning a 2, the rest are 4s
+const TWO_PROBABILITY: f64 = 0.9;
+/// Spawn sequences less likely than this are evaluated statically
+const MIN_PROBABILITY: f64 = 0.0001;
+
+/// Picks moves by averaging over all spawns and maximizing over all moves
+/// up to a fixed number of moves ahead
+///
+/// The search runs on `Bitboard`s, so it only plays 4x4 games. Boards deep
+/// in the search may hold two 32768 tiles, which don't merge there like
+/// they would in `Game`; that's far enough out not to matter for the moves
+/// picked.
+pub struct Expectimax {
+    depth: u32,
+    heuristic: Box<dyn Heuristic>,
+}
+
+impl Expectimax {
+    /// Search `depth` moves ahead using the default heuristic
+    pub fn new(depth: u32) -> Self {
+        Expectimax::with_heuristic(depth, Box::new(WeightedHeuristic::default()))
+    }
+
+    pub fn with_heuristic(depth: u32, heuristic: Box<dyn Heuristic>) -> Self {
+        Expectimax { depth, heuristic }
+    }
+
+    pub fn get_depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Suggest a move for `game`, `None` if there is no legal move or the
+    /// board isn't 4x4
+    pub fn best_move(&self, game: &Game) -> Option<Direction> {
+        self.best_move_for(Bitboard::from_game(game)?)
+    }
+
+    pub fn best_move_for(&self, board: Bitboard) -> Option<Direction> {
+        self.evaluate_moves(board)
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(direction, _)| direction)
+    }
+
+    /// Expected value of every legal move
+    pub fn evaluate_moves(&self, board: Bitboard) -> Vec<(Direction, f64)> {
+        let mut cache = HashMap::new();
+        Direction::ALL
+            .iter()
+            .filter_map(|&direction| {
+                let (next, _) = board.shift(direction);
+                if next == board {
+                    return None;
+                }
+                Some((direction, self.chance(next, self.depth, 1.0, &mut cache)))
+            })
+            .collect()
+    }
+
+    /// Average over every possible spawn on `board`
+    fn chance(
+        &self,
+        board: Bitboard,
+        depth: u32,
+        probability: f64,
+        cache: &mut HashMap<Bitboard, (u32, f64)>,
+    ) -> f64 {
+        if depth == 0 || probability < MIN_PROBABILITY {
+            return self.heuristic.evaluate(board);
+        }
+        if let Some(&(cached_depth, value)) = cache.get(&board) {
+            if cached_depth >= depth {
+                return value;
+            }
+        }
+
+        let empty: Vec<usize> = board.empty_cells().collect();
+        if empty.is_empty() {
+            return self.heuristic.evaluate(board);
+        }
+        let cell_probability = probability / empty.len() as f64;
+        let mut value = 0.0;
+        for &cell in &empty {
+            value += TWO_PROBABILITY
+                * self.max(
+                    board.with_exponent(cell, 1),
+                    depth,
+                    cell_probability * TWO_PROBABILITY,
+                    cache,
+                );
+            value += (1.0 - TWO_PROBABILITY)
+                * self.max(
+                    board.with_exponent(cell, 2),
+                    depth,
+                    cell_probability * (1.0 - TWO_PROBABILITY),
+                    cache,
+                );
+        }
+        value /= empty.len() as f64;
+
+        cache.insert(board, (depth, value));
+        value
+    }
+
+    /// Best value over all moves on `board`, 0 if the game is lost
+    fn max(
+        &self,
+        board: Bitboard,
+        depth: u32,
+        probability: f64,
+        cache: &mut HashMap<Bitboard, (u32, f64)>,
+    ) -> f64 {
+        let mut best = 0.0;
+        for &direction in Direction::ALL.iter() {
+            let (next, _) = board.shift(direction);
+            if next != board {
+                let value = self.chance(next, depth - 1, probability, cache);
+                if value > best {
+                    best = value;
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Default for Expectimax {
+    fn default() -> Self {
+        Expectimax::new(3)
+    }
+}
@@ -0,0 +1,121 @@
+//! Static evaluation of boards for the search.
+
+use crate::bitboard::Bitboard;
+
+/// Scores how promising a board is, higher is better
+///
+/// Results should be positive: lost boards are valued at 0 by the search.
+pub trait Heuristic: Send + Sync {
+    fn evaluate(&self, board: Bitboard) -> f64;
+}
+
+/// Weights for the features `WeightedHeuristic` combines
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights {
+    /// Offset keeping every board that isn't lost above 0
+    pub base: f64,
+    /// Reward per empty cell
+    pub empty: f64,
+    /// Penalty for rows and columns that aren't sorted by tile value
+    pub monotonicity: f64,
+    /// Penalty for differences between neighbouring tiles
+    pub smoothness: f64,
+    /// Reward for keeping the highest tile in a corner
+    pub corner: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            base: 200_000.0,
+            empty: 270.0,
+            monotonicity: 47.0,
+            smoothness: 10.0,
+            corner: 500.0,
+        }
+    }
+}
+
+/// A weighted sum of monotonicity, smoothness, empty cells and corner
+/// placement
+///
+/// The row features are precomputed for all 65536 rows when the heuristic
+/// is created, so evaluating a board is 8 lookups plus the corner check.
+pub struct WeightedHeuristic {
+    weights: Weights,
+    rows: Vec<f64>,
+}
+
+impl WeightedHeuristic {
+    pub fn new(weights: Weights) -> Self {
+        let rows = (0..=u16::MAX)
+            .map(|row| {
+                let board = Bitboard(u64::from(row));
+                evaluate_row(&weights, board.row(0))
+            })
+            .collect();
+        WeightedHeuristic { weights, rows }
+    }
+
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+}
+
+impl Default for WeightedHeuristic {
+    fn default() -> Self {
+        WeightedHeuristic::new(Weights::default())
+    }
+}
+
+impl Heuristic for WeightedHeuristic {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        let transposed = board.transpose();
+        let mut value = self.weights.base;
+        for y in 0..4 {
+            value += self.rows[((board.0 >> (16 * y)) & 0xffff) as usize];
+            value += self.rows[((transposed.0 >> (16 * y)) & 0xffff) as usize];
+        }
+
+        let max = (0..16)
+            .map(|i| board.get_exponent(i % 4, i / 4))
+            .max()
+            .unwrap_or(0);
+        let corners = [(0, 0), (3, 0), (0, 3), (3, 3)];
+        if corners
+            .iter()
+            .any(|&(x, y)| board.get_exponent(x, y) == max)
+        {
+            value += self.weights.corner * f64::from(max);
+        }
+        value
+    }
+}
+
+/// Features of a single row (or column) of exponents
+fn evaluate_row(weights: &Weights, row: [u8; 4]) -> f64 {
+    let empty = row.iter().filter(|&&cell| cell == 0).count() as f64;
+
+    // penalize decreasing in both directions, only the smaller one counts
+    let mut increasing = 0.0;
+    let mut decreasing = 0.0;
+    for pair in row.windows(2) {
+        let (a, b) = (f64::from(pair[0]).powi(4), f64::from(pair[1]).powi(4));
+        if a > b {
+            decreasing += a - b;
+        } else {
+            increasing += b - a;
+        }
+    }
+    let monotonicity = increasing.min(decreasing);
+
+    // compare each tile with the next non-empty one
+    let tiles: Vec<f64> = row
+        .iter()
+        .filter(|&&cell| cell != 0)
+        .map(|&cell| f64::from(cell))
+        .collect();
+    let smoothness: f64 = tiles.windows(2).map(|pair| (pair[0] - pair[1]).abs()).sum();
+
+    weights.empty * empty - weights.monotonicity * monotonicity - weights.smoothness * smoothness
+}
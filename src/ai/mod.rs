@@ -0,0 +1,13 @@
+//! Computer players built on top of the engine.
+
+pub mod expectimax;
+pub mod heuristic;
+pub mod montecarlo;
+pub mod ntuple;
+pub mod strategy;
+
+pub use self::expectimax::Expectimax;
+pub use self::heuristic::{Heuristic, WeightedHeuristic, Weights};
+pub use self::montecarlo::MonteCarlo;
+pub use self::ntuple::{Evaluator, NTupleNetwork, NetworkError, NetworkHeuristic};
+pub use self::strategy::Strategy;
@@ -0,0 +1,226 @@
+//! Picking moves by playing random games to the end.
+//!
+//! For every legal move `MonteCarlo` plays out many games from the board
+//! after it, making random moves until nothing moves any more, and picks
+//! the move whose games end with the highest average score. It needs no
+//! heuristic and plays boards of any shape.
+
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai::strategy::{self, Strategy};
+use crate::game::{Direction, Game};
+use crate::rng::{Rng, SplitMix64};
+
+/// Rollouts per move unless told otherwise
+pub const DEFAULT_ROLLOUTS: usize = 100;
+
+/// Reads the time in milliseconds, only differences between two readings
+/// matter
+pub type Clock = fn() -> f64;
+
+/// The system's clock, or on WebAssembly a clock that never moves since
+/// there is no portable way to read the time there
+///
+/// Browsers set their own clock, e.g. one reading `performance.now()`.
+pub fn system_clock() -> f64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        0.0
+    }
+}
+
+/// Picks the move whose random playouts score best on average
+#[derive(Debug)]
+pub struct MonteCarlo {
+    rollouts: usize,
+    /// Milliseconds to spend on a move, `None` to always play every rollout
+    budget: Option<f64>,
+    threads: usize,
+    clock: Clock,
+    rng: SplitMix64,
+}
+
+impl MonteCarlo {
+    /// Play `rollouts` games after every legal move, on as many threads as
+    /// there are cores, with randomness started from `seed`
+    pub fn new(rollouts: usize, seed: u64) -> Self {
+        MonteCarlo {
+            rollouts: rollouts.max(1),
+            budget: None,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            clock: system_clock,
+            rng: SplitMix64::from_seed(seed),
+        }
+    }
+
+    pub fn get_rollouts(&self) -> usize {
+        self.rollouts
+    }
+
+    /// Stop starting new rollouts after `budget` milliseconds per move
+    ///
+    /// Every legal move gets at least one rollout however small the budget.
+    pub fn set_budget(&mut self, budget: Option<f64>) {
+        self.budget = budget;
+    }
+
+    /// Spread the rollouts over `threads` threads, 1 plays them on the
+    /// calling thread, which is all WebAssembly can do
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Suggest a move for `game`, `None` if there is no legal move
+    pub fn best_move(&mut self, game: &Game) -> Option<Direction> {
+        self.evaluate_moves(game)
+            .into_iter()
+            .rev()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(direction, _)| direction)
+    }
+
+    /// Average final score of the rollouts after every legal move
+    pub fn evaluate_moves(&mut self, game: &Game) -> Vec<(Direction, f64)> {
+        let moves = strategy::legal_moves(game);
+        if moves.is_empty() {
+            return Vec::new();
+        }
+        let clock = self.clock;
+        let deadline = self.budget.map(|budget| clock() + budget);
+        let threads = self.threads.min(self.rollouts);
+        // Every thread plays its share of the rounds with its own seed
+        let shares: Vec<(usize, u64)> = (0..threads)
+            .map(|i| {
+                let rounds = self.rollouts / threads + usize::from(i < self.rollouts % threads);
+                (rounds, self.rng.next_u64())
+            })
+            .collect();
+
+        let totals: Vec<Vec<(f64, usize)>> = match threads {
+            1 => vec![rollouts(&moves, shares[0].0, shares[0].1, deadline, clock)],
+            _ => thread::scope(|scope| {
+                let handles: Vec<_> = shares
+                    .iter()
+                    .map(|&(rounds, seed)| {
+                        // Games can't be shared between threads, only sent
+                        let moves = moves.clone();
+                        scope.spawn(move || rollouts(&moves, rounds, seed, deadline, clock))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("rollouts don't panic"))
+                    .collect()
+            }),
+        };
+
+        moves
+            .iter()
+            .enumerate()
+            .map(|(i, &(direction, _))| {
+                let (sum, count) = totals.iter().fold((0.0, 0), |(sum, count), totals| {
+                    (sum + totals[i].0, count + totals[i].1)
+                });
+                (direction, sum / count.max(1) as f64)
+            })
+            .collect()
+    }
+}
+
+impl Strategy for MonteCarlo {
+    fn name(&self) -> &str {
+        "montecarlo"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        self.best_move(game)
+    }
+}
+
+/// Play up to `rounds` rollouts after each of `moves`, one per move at a
+/// time until `deadline`, returning the sum of their final scores and their
+/// number for each move
+fn rollouts(
+    moves: &[(Direction, Game)],
+    rounds: usize,
+    seed: u64,
+    deadline: Option<f64>,
+    clock: Clock,
+) -> Vec<(f64, usize)> {
+    let mut rng = SplitMix64::from_seed(seed);
+    let mut totals = vec![(0.0, 0); moves.len()];
+    for round in 0..rounds {
+        if round > 0 && deadline.is_some_and(|deadline| clock() >= deadline) {
+            break;
+        }
+        for ((sum, count), (_, after)) in totals.iter_mut().zip(moves) {
+            *sum += rollout(after, &mut rng) as f64;
+            *count += 1;
+        }
+    }
+    totals
+}
+
+/// Spawn a tile on `after`, the game right after a move, and make random
+/// moves until the game is over, returning its final score
+fn rollout(after: &Game, rng: &mut SplitMix64) -> usize {
+    let mut game = after.clone();
+    // Clones would all spawn the same tiles otherwise
+    game.reseed(rng.next_u64());
+    game.keep_playing();
+    game.seed_cell();
+    while !game.is_finished() {
+        // A game that isn't over always has a move, try until one works
+        while !game.play(&Direction::ALL[rng.below(4)]).moved() {}
+    }
+    game.get_score()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_picks_the_same_moves() {
+        let mut game = Game::from_shape(4, 4, 3);
+        let mut states: Vec<usize> = (0..16).map(|i| 2 << (i % 7)).collect();
+        states[..5].iter_mut().for_each(|state| *state = 0);
+        game.set_states(states).unwrap();
+        game.seed_cell();
+
+        let evaluate = |seed| {
+            let mut montecarlo = MonteCarlo::new(50, seed);
+            montecarlo.set_threads(3);
+            let first = montecarlo.evaluate_moves(&game);
+            // The generator moves on, so the next call plays other games
+            let second = montecarlo.evaluate_moves(&game);
+            (first, second, montecarlo.best_move(&game))
+        };
+        let (first, second, best) = evaluate(8);
+        assert_eq!(evaluate(8), (first.clone(), second.clone(), best));
+        assert_ne!(first, second);
+        assert_eq!(
+            first
+                .iter()
+                .map(|&(direction, _)| direction)
+                .collect::<Vec<_>>(),
+            strategy::legal_moves(&game)
+                .into_iter()
+                .map(|(direction, _)| direction)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! N-tuple networks valuing boards by the tiles in a few groups of cells.
+//!
+//! Each pattern is a tuple of cells. The network keeps a weight for every
+//! combination of tiles those cells can hold and values a board by adding
+//! up the weights of every pattern, looked at under each symmetry of the
+//! board. `rs2048-train` learns the weights by temporal-difference learning
+//! on afterstates, the boards right after a move and before a tile spawns,
+//! so a value is the number of points the rest of the game is expected to
+//! score.
+//!
+//! Networks are saved as a line of JSON describing them followed by the
+//! weights as little-endian 32 bit floats.
+
+use std::cmp::Ordering;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ai::heuristic::Heuristic;
+use crate::ai::strategy::{self, Strategy};
+use crate::bitboard::Bitboard;
+use crate::game::{Direction, Game};
+use crate::save::LoadError;
+
+/// Current version of the weights format
+pub const VERSION: u64 = 1;
+
+/// Most cells in a pattern, longer ones would need gigabytes of weights
+pub const MAX_TUPLE: usize = 6;
+
+/// Tile exponents told apart, larger tiles count as 2^15
+const EXPONENTS: usize = 16;
+
+/// Patterns used unless others are given, as (x, y) cells: the top two
+/// rows and three 2x2 squares, small enough to train in minutes. The
+/// symmetries of the board turn them into every other row and square.
+const DEFAULT_PATTERNS: [[(usize, usize); 4]; 5] = [
+    [(0, 0), (1, 0), (2, 0), (3, 0)],
+    [(0, 1), (1, 1), (2, 1), (3, 1)],
+    [(0, 0), (1, 0), (0, 1), (1, 1)],
+    [(1, 0), (2, 0), (1, 1), (2, 1)],
+    [(1, 1), (2, 1), (1, 2), (2, 2)],
+];
+
+/// The default patterns as row-major cell indices on a board of `width`
+/// by `height` cells, `None` if it's smaller than 4x3
+pub fn default_patterns(width: usize, height: usize) -> Option<Vec<Vec<usize>>> {
+    if width < 4 || height < 3 {
+        return None;
+    }
+    let patterns = DEFAULT_PATTERNS
+        .iter()
+        .map(|pattern| pattern.iter().map(|&(x, y)| y * width + x).collect())
+        .collect();
+    Some(patterns)
+}
+
+/// Parse patterns like "0,1,2,3;0,1,4,5", cell indices separated by
+/// commas and patterns by semicolons
+pub fn parse_patterns(text: &str) -> Option<Vec<Vec<usize>>> {
+    text.split(';')
+        .map(|pattern| {
+            pattern
+                .split(',')
+                .map(|cell| cell.trim().parse().ok())
+                .collect()
+        })
+        .collect()
+}
+
+/// Exponent of every cell of `game`, row by row, 0 for empty cells
+pub fn exponents(game: &Game) -> Vec<u8> {
+    game.get_states()
+        .into_iter()
+        .map(|value| match value {
+            0 => 0,
+            value => value.trailing_zeros().min(EXPONENTS as u32 - 1) as u8,
+        })
+        .collect()
+}
+
+/// What the JSON line in front of the weights describes
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u64,
+    width: usize,
+    height: usize,
+    patterns: Vec<Vec<usize>>,
+    games: u64,
+    /// Missing from files written before it was kept, which all started
+    /// from the default seed 0
+    #[serde(default)]
+    first_seed: u64,
+}
+
+/// Weights for a set of patterns on boards of one shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct NTupleNetwork {
+    width: usize,
+    height: usize,
+    patterns: Vec<Vec<usize>>,
+    /// The cells of every pattern under each symmetry of the board
+    features: Vec<Vec<Vec<usize>>>,
+    weights: Vec<Vec<f32>>,
+    /// Games trained on so far
+    games: u64,
+    /// Seed of the first game trained on, later games count up from it
+    first_seed: u64,
+}
+
+impl NTupleNetwork {
+    /// An untrained network for boards of `width` by `height` cells
+    ///
+    /// Fails if a pattern is empty, longer than `MAX_TUPLE`, repeats a cell
+    /// or reaches past the board.
+    pub fn new(
+        width: usize,
+        height: usize,
+        patterns: Vec<Vec<usize>>,
+    ) -> Result<Self, NetworkError> {
+        check_patterns(width, height, &patterns)?;
+        let features = patterns
+            .iter()
+            .map(|pattern| symmetries(width, height, pattern))
+            .collect();
+        let weights = patterns
+            .iter()
+            .map(|pattern| vec![0.0; EXPONENTS.pow(pattern.len() as u32)])
+            .collect();
+        Ok(NTupleNetwork {
+            width,
+            height,
+            patterns,
+            features,
+            weights,
+            games: 0,
+            first_seed: 0,
+        })
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_patterns(&self) -> &[Vec<usize>] {
+        &self.patterns
+    }
+
+    pub fn get_games(&self) -> u64 {
+        self.games
+    }
+
+    /// Count another game trained on
+    pub fn add_game(&mut self) {
+        self.games += 1;
+    }
+
+    pub fn get_first_seed(&self) -> u64 {
+        self.first_seed
+    }
+
+    pub fn set_first_seed(&mut self, seed: u64) {
+        self.first_seed = seed;
+    }
+
+    /// Points the rest of the game is expected to score from a board with
+    /// the tile `exponents` of every cell
+    pub fn value(&self, exponents: &[u8]) -> f32 {
+        self.features
+            .iter()
+            .zip(&self.weights)
+            .map(|(features, weights)| {
+                features
+                    .iter()
+                    .map(|cells| weights[index(exponents, cells)])
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+
+    /// Move the value of the board with `exponents` by `step`, shared out
+    /// over the weights that make it up
+    pub fn update(&mut self, exponents: &[u8], step: f32) {
+        let count: usize = self.features.iter().map(Vec::len).sum();
+        let step = step / count as f32;
+        for (features, weights) in self.features.iter().zip(&mut self.weights) {
+            for cells in features {
+                weights[index(exponents, cells)] += step;
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = Header {
+            version: VERSION,
+            width: self.width,
+            height: self.height,
+            patterns: self.patterns.clone(),
+            games: self.games,
+            first_seed: self.first_seed,
+        };
+        let mut bytes = serde_json::to_vec(&header).expect("a header is always valid JSON");
+        bytes.push(b'\n');
+        for weight in self.weights.iter().flatten() {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Read the weights file at `path`, as written by `rs2048-train`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkError> {
+        let bytes = std::fs::read(path).map_err(NetworkError::Io)?;
+        NTupleNetwork::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError> {
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(LoadError::UnexpectedEnd)?;
+        let value: Value = serde_json::from_slice(&bytes[..end]).map_err(LoadError::Json)?;
+        let header: Header = match value.get("version").and_then(Value::as_u64) {
+            Some(VERSION) => serde_json::from_value(value).map_err(LoadError::Json)?,
+            Some(version) => return Err(LoadError::UnsupportedVersion(version).into()),
+            None => return Err(LoadError::MissingVersion.into()),
+        };
+        // A short file with long patterns would otherwise make room for
+        // gigabytes of weights it doesn't hold
+        check_patterns(header.width, header.height, &header.patterns)?;
+        let count: usize = header
+            .patterns
+            .iter()
+            .map(|pattern| EXPONENTS.pow(pattern.len() as u32))
+            .sum();
+        let weights = &bytes[end + 1..];
+        match weights.len().cmp(&(4 * count)) {
+            Ordering::Less => return Err(LoadError::UnexpectedEnd.into()),
+            Ordering::Greater => return Err(LoadError::TrailingData.into()),
+            Ordering::Equal => {}
+        }
+
+        let mut network = NTupleNetwork::new(header.width, header.height, header.patterns)?;
+        network.games = header.games;
+        network.first_seed = header.first_seed;
+        let chunks = weights.chunks_exact(4);
+        for (weight, chunk) in network.weights.iter_mut().flatten().zip(chunks) {
+            *weight = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        Ok(network)
+    }
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    /// The weights file couldn't be read
+    Io(std::io::Error),
+    /// The weights file isn't in the format written by `to_bytes`
+    Load(LoadError),
+    /// A pattern is empty, too long, repeats a cell or reaches past the
+    /// board
+    InvalidPattern(Vec<usize>),
+    /// The network values boards the search can't play, it only plays 4x4
+    UnsupportedShape { width: usize, height: usize },
+}
+
+impl From<LoadError> for NetworkError {
+    fn from(error: LoadError) -> Self {
+        NetworkError::Load(error)
+    }
+}
+
+impl std::fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NetworkError::Io(error) => write!(f, "{}", error),
+            NetworkError::Load(error) => write!(f, "{}", error),
+            NetworkError::InvalidPattern(pattern) => write!(f, "invalid pattern {:?}", pattern),
+            NetworkError::UnsupportedShape { width, height } => write!(
+                f,
+                "the weights are for {}x{} boards, the search only plays 4x4",
+                width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Check that every pattern holds 1 to `MAX_TUPLE` different cells of a
+/// board of `width` by `height` cells
+fn check_patterns(
+    width: usize,
+    height: usize,
+    patterns: &[Vec<usize>],
+) -> Result<(), NetworkError> {
+    let cells = width.saturating_mul(height);
+    for pattern in patterns {
+        let repeats = (1..pattern.len()).any(|i| pattern[..i].contains(&pattern[i]));
+        if pattern.is_empty()
+            || pattern.len() > MAX_TUPLE
+            || repeats
+            || pattern.iter().any(|&cell| cell >= cells)
+        {
+            return Err(NetworkError::InvalidPattern(pattern.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Index of the weight for the tiles on `cells`
+fn index(exponents: &[u8], cells: &[usize]) -> usize {
+    cells.iter().fold(0, |index, &cell| {
+        index * EXPONENTS + usize::from(exponents[cell])
+    })
+}
+
+/// The cells of `pattern` under every mirroring of the board, and every
+/// rotation too if it's square
+fn symmetries(width: usize, height: usize, pattern: &[usize]) -> Vec<Vec<usize>> {
+    let square = width == height;
+    (0..if square { 8 } else { 4 })
+        .map(|symmetry| {
+            pattern
+                .iter()
+                .map(|&cell| {
+                    let (mut x, mut y) = (cell % width, cell / width);
+                    if symmetry & 1 != 0 {
+                        x = width - 1 - x;
+                    }
+                    if symmetry & 2 != 0 {
+                        y = height - 1 - y;
+                    }
+                    if symmetry & 4 != 0 {
+                        std::mem::swap(&mut x, &mut y);
+                    }
+                    y * width + x
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A trained network shared by the search and the players using it
+///
+/// As a `Strategy` it plays the move whose points plus the value of the
+/// board after it are the highest, the way it was trained. A 4x4 network
+/// also values boards for `Expectimax` through `heuristic`.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    network: Arc<NTupleNetwork>,
+}
+
+impl Evaluator {
+    pub fn new(network: NTupleNetwork) -> Self {
+        Evaluator {
+            network: Arc::new(network),
+        }
+    }
+
+    pub fn get_network(&self) -> &NTupleNetwork {
+        &self.network
+    }
+
+    /// Whether the network was trained on boards shaped like `game`'s
+    pub fn fits(&self, game: &Game) -> bool {
+        (game.get_width(), game.get_height()) == (self.network.width, self.network.height)
+    }
+
+    /// The network as a heuristic for `Expectimax`, which fails unless it
+    /// was trained on 4x4 boards like the ones the search plays
+    pub fn heuristic(&self) -> Result<NetworkHeuristic, NetworkError> {
+        match (self.network.width, self.network.height) {
+            (4, 4) => Ok(NetworkHeuristic {
+                network: self.network.clone(),
+            }),
+            (width, height) => Err(NetworkError::UnsupportedShape { width, height }),
+        }
+    }
+}
+
+/// A 4x4 network valuing the boards `Expectimax` searches, made by
+/// `Evaluator::heuristic`
+#[derive(Clone, Debug)]
+pub struct NetworkHeuristic {
+    network: Arc<NTupleNetwork>,
+}
+
+impl Heuristic for NetworkHeuristic {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        let exponents: Vec<u8> = (0..16)
+            .map(|cell| board.get_exponent(cell % 4, cell / 4))
+            .collect();
+        // The search values lost boards at 0, nothing should be below them
+        f64::from(self.network.value(&exponents)).max(0.0)
+    }
+}
+
+impl Strategy for Evaluator {
+    fn name(&self) -> &str {
+        "ntuple"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        if !self.fits(game) {
+            return None;
+        }
+        let score = game.get_score() as f32;
+        strategy::legal_moves(game)
+            .into_iter()
+            .map(|(direction, next)| {
+                let points = next.get_score() as f32 - score;
+                (direction, points + self.network.value(&exponents(&next)))
+            })
+            .rev()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(direction, _)| direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_4x4_networks_make_heuristics() {
+        let network = NTupleNetwork::new(5, 5, vec![vec![20, 21, 22, 23]]).unwrap();
+        assert!(matches!(
+            Evaluator::new(network).heuristic(),
+            Err(NetworkError::UnsupportedShape {
+                width: 5,
+                height: 5
+            })
+        ));
+        let network = NTupleNetwork::new(4, 4, default_patterns(4, 4).unwrap()).unwrap();
+        let heuristic = Evaluator::new(network).heuristic().unwrap();
+        assert_eq!(heuristic.evaluate(Bitboard::default()), 0.0);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        for pattern in [
+            vec![],
+            vec![0, 1, 0],
+            vec![0, 16],
+            (0..=MAX_TUPLE).collect(),
+        ] {
+            assert!(matches!(
+                NTupleNetwork::new(4, 4, vec![pattern.clone()]),
+                Err(NetworkError::InvalidPattern(invalid)) if invalid == pattern
+            ));
+        }
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut network = NTupleNetwork::new(4, 4, vec![vec![0, 1], vec![4, 5, 6]]).unwrap();
+        network.weights[1][7] = 1.5;
+        network.add_game();
+        network.set_first_seed(42);
+        let loaded = NTupleNetwork::from_bytes(&network.to_bytes()).unwrap();
+        assert_eq!(loaded.weights, network.weights);
+        assert_eq!(loaded.get_games(), 1);
+        assert_eq!(loaded.get_first_seed(), 42);
+    }
+
+    #[test]
+    fn checks_the_size_before_loading_weights() {
+        let network = NTupleNetwork::new(4, 4, vec![vec![0, 1]]).unwrap();
+        let bytes = network.to_bytes();
+        assert!(matches!(
+            NTupleNetwork::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NetworkError::Load(LoadError::UnexpectedEnd))
+        ));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(matches!(
+            NTupleNetwork::from_bytes(&longer),
+            Err(NetworkError::Load(LoadError::TrailingData))
+        ));
+
+        // A header alone claiming a thousand 6-cell patterns
+        let patterns = vec![(0..MAX_TUPLE).collect::<Vec<_>>(); 1000];
+        let header = serde_json::to_string(&Header {
+            version: VERSION,
+            width: 4,
+            height: 4,
+            patterns,
+            games: 0,
+            first_seed: 0,
+        })
+        .unwrap();
+        assert!(matches!(
+            NTupleNetwork::from_bytes(format!("{}\n", header).as_bytes()),
+            Err(NetworkError::Load(LoadError::UnexpectedEnd))
+        ));
+    }
+}
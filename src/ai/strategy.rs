@@ -0,0 +1,237 @@
+//! Bots that pick a move by looking at the game.
+//!
+//! Anything implementing `Strategy` can drive the terminal and browser
+//! autoplay and the simulator. Frontends look strategies up with `named`,
+//! so a new bot only needs to be added there to show up everywhere.
+
+use crate::ai::montecarlo::{MonteCarlo, DEFAULT_ROLLOUTS};
+use crate::ai::Expectimax;
+use crate::game::{Direction, Game};
+use crate::rng::{Rng, SplitMix64};
+
+/// Names of the strategies `named` knows, the first one is the default
+pub const STRATEGIES: [&str; 6] = [
+    "expectimax",
+    "montecarlo",
+    "snake",
+    "corner",
+    "greedy",
+    "random",
+];
+
+/// Picks moves for a game
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    /// The move to make in `game`, `None` if there is no legal move or
+    /// the strategy can't play this game
+    fn next_move(&mut self, game: &Game) -> Option<Direction>;
+}
+
+/// The strategy called `name` with its default settings, `seed` starts
+/// strategies that make random choices
+pub fn named(name: &str, seed: u64) -> Option<Box<dyn Strategy>> {
+    match name {
+        "expectimax" => Some(Box::new(Expectimax::default())),
+        "montecarlo" => Some(Box::new(MonteCarlo::new(DEFAULT_ROLLOUTS, seed))),
+        "snake" => Some(Box::new(Snake)),
+        "corner" => Some(Box::new(Corner)),
+        "greedy" => Some(Box::new(Greedy)),
+        "random" => Some(Box::new(RandomLegal::from_seed(seed))),
+        _ => None,
+    }
+}
+
+/// Every move that changes the board, in the order of `Direction::ALL`,
+/// along with the game after sliding the tiles but before a new one spawns
+pub fn legal_moves(game: &Game) -> Vec<(Direction, Game)> {
+    Direction::ALL
+        .iter()
+        .filter_map(|&direction| {
+            let mut next = game.clone();
+            next.set_history_capacity(0);
+            match next.shift(&direction).moved() {
+                true => Some((direction, next)),
+                false => None,
+            }
+        })
+        .collect()
+}
+
+/// Plays any legal move
+#[derive(Debug)]
+pub struct RandomLegal {
+    rng: SplitMix64,
+}
+
+impl RandomLegal {
+    pub fn from_seed(seed: u64) -> Self {
+        RandomLegal {
+            rng: SplitMix64::from_seed(seed),
+        }
+    }
+}
+
+impl Strategy for RandomLegal {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        let moves = legal_moves(game);
+        match moves.is_empty() {
+            true => None,
+            false => Some(moves[self.rng.below(moves.len())].0),
+        }
+    }
+}
+
+/// Plays the move scoring the most points right away
+#[derive(Debug)]
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        // The first of equally good moves
+        legal_moves(game)
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, next)| next.get_score())
+            .map(|(direction, _)| direction)
+    }
+}
+
+/// Keeps the tiles in the bottom left corner by playing down, then left,
+/// then right and only then up
+#[derive(Debug)]
+pub struct Corner;
+
+impl Strategy for Corner {
+    fn name(&self) -> &str {
+        "corner"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        let moves = legal_moves(game);
+        [
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+        ]
+        .iter()
+        .copied()
+        .find(|direction| moves.iter().any(|(legal, _)| legal == direction))
+    }
+}
+
+/// Lines the tiles up in a snake from the bottom left corner, along the
+/// bottom row and back along the one above it and so on, largest first
+///
+/// Every legal move is rated by weighing its tiles more the earlier they
+/// come along the snake.
+#[derive(Debug)]
+pub struct Snake;
+
+impl Snake {
+    /// How much more a cell weighs than the next one along the snake
+    const RATIO: f64 = 4.0;
+
+    fn rate(game: &Game) -> f64 {
+        let (width, height) = (game.get_width(), game.get_height());
+        let mut weight = 1.0;
+        let mut rating = 0.0;
+        // Walk the snake backwards, from its tail to the corner
+        for y in 0..height {
+            for column in 0..width {
+                // Rows alternate direction, the bottom one runs left to right
+                let x = match (height - 1 - y) % 2 {
+                    0 => width - 1 - column,
+                    _ => column,
+                };
+                rating += weight * game.get_state(x, y) as f64;
+                weight *= Snake::RATIO;
+            }
+        }
+        rating
+    }
+}
+
+impl Strategy for Snake {
+    fn name(&self) -> &str {
+        "snake"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        legal_moves(game)
+            .into_iter()
+            .map(|(direction, next)| (direction, Snake::rate(&next)))
+            .rev()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(direction, _)| direction)
+    }
+}
+
+impl Strategy for Expectimax {
+    fn name(&self) -> &str {
+        "expectimax"
+    }
+
+    fn next_move(&mut self, game: &Game) -> Option<Direction> {
+        self.best_move(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies_only_play_legal_moves() {
+        for &name in STRATEGIES.iter() {
+            for &(width, height) in &[(4, 4), (5, 4)] {
+                let mut strategy = named(name, 1).unwrap();
+                assert_eq!(strategy.name(), name);
+                // A crowded board keeps the random playouts short
+                let mut game = Game::from_shape(width, height, 2);
+                let mut states: Vec<usize> = (1..=width * height).map(|i| 2 << (i % 9)).collect();
+                states[..width + 1].iter_mut().for_each(|state| *state = 0);
+                game.set_states(states).unwrap();
+                game.seed_cell();
+                for _ in 0..20 {
+                    let direction = match strategy.next_move(&game) {
+                        Some(direction) => direction,
+                        None => break,
+                    };
+                    let legal: Vec<Direction> = legal_moves(&game)
+                        .into_iter()
+                        .map(|(direction, _)| direction)
+                        .collect();
+                    assert!(
+                        legal.contains(&direction),
+                        "{} played {:?}",
+                        name,
+                        direction
+                    );
+                    game.play(&direction);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strategies_give_up_on_finished_games() {
+        let mut game = Game::new();
+        game.set_states(vec![2, 4, 2, 4, 4, 2, 4, 2, 2, 4, 2, 4, 4, 2, 4, 2])
+            .unwrap();
+        assert_eq!(game.seed_cell(), None);
+        assert!(game.is_finished());
+        for &name in STRATEGIES.iter() {
+            assert_eq!(named(name, 1).unwrap().next_move(&game), None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Play many games without a frontend and report how a strategy did.
+//!
+//! Games are played from consecutive seeds on every core, so a run can be
+//! repeated exactly by passing the same seed, size and strategy again.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use rs2048::ai::strategy::{self, Strategy, STRATEGIES};
+use rs2048::ai::{Evaluator, Expectimax, MonteCarlo, NTupleNetwork};
+use rs2048::game::parse_shape;
+use rs2048::Game;
+
+const USAGE: &str = "usage: rs2048-sim [--games N] [--seed FIRST] [--size WIDTHxHEIGHT] \
+[--strategy expectimax|montecarlo|ntuple|snake|corner|greedy|random] [--depth N] [--rollouts N] \
+[--budget MS] [--weights FILE] [--threads N] [--format table|json|csv]";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+struct Options {
+    games: u64,
+    first_seed: u64,
+    width: usize,
+    height: usize,
+    strategy: String,
+    /// How far expectimax looks ahead
+    depth: u32,
+    /// Games Monte Carlo plays out after every legal move
+    rollouts: usize,
+    /// Milliseconds Monte Carlo may spend on a move
+    budget: Option<f64>,
+    /// Trained n-tuple network for `ntuple` and `expectimax`
+    evaluator: Option<Evaluator>,
+    threads: usize,
+    format: Format,
+}
+
+/// How a single game ended
+#[derive(Clone, Debug, Serialize)]
+struct GameResult {
+    seed: u64,
+    score: usize,
+    max_tile: usize,
+    moves: usize,
+    won: bool,
+}
+
+/// Spread of the scores
+#[derive(Debug, Serialize)]
+struct Distribution {
+    min: usize,
+    p25: usize,
+    median: usize,
+    p75: usize,
+    p90: usize,
+    max: usize,
+    mean: f64,
+}
+
+/// How many games ended with a tile as their largest
+#[derive(Debug, Serialize)]
+struct TileCount {
+    tile: usize,
+    games: usize,
+    /// Percentage of games ending with exactly this tile
+    share: f64,
+    /// Percentage of games reaching at least this tile
+    reached: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    strategy: String,
+    width: usize,
+    height: usize,
+    games: usize,
+    first_seed: u64,
+    threads: usize,
+    seconds: f64,
+    games_per_second: f64,
+    scores: Distribution,
+    max_tiles: Vec<TileCount>,
+    results: Vec<GameResult>,
+}
+
+/// A fresh strategy for the game started from `seed`
+fn new_strategy(options: &Options, seed: u64) -> Box<dyn Strategy> {
+    match options.strategy.as_str() {
+        "expectimax" => match &options.evaluator {
+            Some(evaluator) => Box::new(Expectimax::with_heuristic(
+                options.depth,
+                Box::new(
+                    evaluator
+                        .heuristic()
+                        .expect("expectimax and the weights are checked to be 4x4 when parsing"),
+                ),
+            )),
+            None => Box::new(Expectimax::new(options.depth)),
+        },
+        "ntuple" => Box::new(
+            options
+                .evaluator
+                .clone()
+                .expect("weights are checked when parsing"),
+        ),
+        "montecarlo" => {
+            let mut montecarlo = MonteCarlo::new(options.rollouts, seed);
+            montecarlo.set_budget(options.budget);
+            // Games already run on every core
+            montecarlo.set_threads(1);
+            Box::new(montecarlo)
+        }
+        name => strategy::named(name, seed).expect("strategies are checked when parsing"),
+    }
+}
+
+/// Play the game started from `seed` to the end
+fn play(options: &Options, seed: u64) -> GameResult {
+    let mut game = Game::from_shape(options.width, options.height, seed);
+    // Nothing is undone and reaching the target doesn't end the game
+    game.set_history_capacity(0);
+    game.keep_playing();
+    game.seed_cell();
+    game.seed_cell();
+    let mut strategy = new_strategy(options, seed);
+    while !game.is_finished() {
+        match strategy.next_move(&game) {
+            Some(direction) => {
+                game.play(&direction);
+            }
+            None => break,
+        }
+    }
+    GameResult {
+        seed,
+        score: game.get_score(),
+        max_tile: game.get_max_tile(),
+        moves: game.get_moves(),
+        won: game.is_won(),
+    }
+}
+
+/// Play every game on `options.threads` threads, in the order of their
+/// seeds
+fn run(options: &Options) -> Vec<GameResult> {
+    let next = AtomicU64::new(0);
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..options.threads {
+            scope.spawn(|| {
+                let mut played = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= options.games {
+                        break;
+                    }
+                    played.push(play(options, options.first_seed.wrapping_add(index)));
+                }
+                results.lock().unwrap().extend(played);
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|result| result.seed.wrapping_sub(options.first_seed));
+    results
+}
+
+fn report(options: &Options, results: Vec<GameResult>, seconds: f64) -> Report {
+    let mut scores: Vec<usize> = results.iter().map(|result| result.score).collect();
+    scores.sort_unstable();
+    let percentile = |p: usize| scores[(scores.len() - 1) * p / 100];
+    let distribution = Distribution {
+        min: percentile(0),
+        p25: percentile(25),
+        median: percentile(50),
+        p75: percentile(75),
+        p90: percentile(90),
+        max: percentile(100),
+        mean: scores.iter().sum::<usize>() as f64 / scores.len() as f64,
+    };
+
+    let games = results.len();
+    let percent = |count: usize| 100.0 * count as f64 / games as f64;
+    let mut tiles: Vec<usize> = results.iter().map(|result| result.max_tile).collect();
+    tiles.sort_unstable();
+    tiles.dedup();
+    let max_tiles = tiles
+        .into_iter()
+        .map(|tile| {
+            let ended = results
+                .iter()
+                .filter(|result| result.max_tile == tile)
+                .count();
+            let reached = results
+                .iter()
+                .filter(|result| result.max_tile >= tile)
+                .count();
+            TileCount {
+                tile,
+                games: ended,
+                share: percent(ended),
+                reached: percent(reached),
+            }
+        })
+        .collect();
+
+    Report {
+        strategy: options.strategy.clone(),
+        width: options.width,
+        height: options.height,
+        games,
+        first_seed: options.first_seed,
+        threads: options.threads,
+        seconds,
+        games_per_second: games as f64 / seconds.max(f64::EPSILON),
+        scores: distribution,
+        max_tiles,
+        results,
+    }
+}
+
+fn print_table(report: &Report) {
+    println!("Strategy  {}", report.strategy);
+    println!("Board     {}x{}", report.width, report.height);
+    println!(
+        "Games     {} (seeds {} to {})",
+        report.games,
+        report.first_seed,
+        report
+            .first_seed
+            .wrapping_add(report.games as u64)
+            .wrapping_sub(1)
+    );
+    println!(
+        "Time      {:.2}s on {} thread{}, {:.1} games/s",
+        report.seconds,
+        report.threads,
+        if report.threads == 1 { "" } else { "s" },
+        report.games_per_second
+    );
+
+    let scores = &report.scores;
+    println!();
+    println!(
+        "{:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "min", "p25", "median", "p75", "p90", "max", "mean"
+    );
+    println!(
+        "{:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10.1}",
+        scores.min, scores.p25, scores.median, scores.p75, scores.p90, scores.max, scores.mean
+    );
+
+    println!();
+    println!(
+        "{:>10} {:>10} {:>10} {:>10}",
+        "max tile", "games", "share", "reached"
+    );
+    for tile in &report.max_tiles {
+        println!(
+            "{:>10} {:>10} {:>9.1}% {:>9.1}%",
+            tile.tile, tile.games, tile.share, tile.reached
+        );
+    }
+}
+
+fn print_csv(report: &Report) {
+    println!("seed,score,max_tile,moves,won");
+    for result in &report.results {
+        println!(
+            "{},{},{},{},{}",
+            result.seed, result.score, result.max_tile, result.moves, result.won
+        );
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        games: 1000,
+        first_seed: 0,
+        width: 4,
+        height: 4,
+        strategy: "corner".to_string(),
+        depth: 2,
+        rollouts: 100,
+        budget: None,
+        evaluator: None,
+        threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        format: Format::Table,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let argument = args.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "--games" => options.games = argument.parse().unwrap_or_else(|_| usage()),
+            "--seed" => options.first_seed = argument.parse().unwrap_or_else(|_| usage()),
+            "--size" => {
+                let (width, height) = parse_shape(&argument).unwrap_or_else(|| usage());
+                options.width = width;
+                options.height = height;
+            }
+            "--strategy" if STRATEGIES.contains(&argument.as_str()) || argument == "ntuple" => {
+                options.strategy = argument
+            }
+            "--weights" => {
+                options.evaluator = match NTupleNetwork::load(&argument) {
+                    Ok(network) => Some(Evaluator::new(network)),
+                    Err(error) => {
+                        eprintln!("Failed to load weights from {}: {}", argument, error);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--depth" => options.depth = argument.parse().unwrap_or_else(|_| usage()),
+            "--rollouts" => {
+                options.rollouts = match argument.parse() {
+                    Ok(rollouts) if rollouts > 0 => rollouts,
+                    _ => usage(),
+                }
+            }
+            "--budget" => options.budget = Some(argument.parse().unwrap_or_else(|_| usage())),
+            "--threads" => {
+                options.threads = match argument.parse() {
+                    Ok(threads) if threads > 0 => threads,
+                    _ => usage(),
+                }
+            }
+            "--format" => {
+                options.format = match argument.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => usage(),
+                }
+            }
+            _ => usage(),
+        }
+    }
+    if options.games == 0 {
+        usage();
+    }
+    if options.strategy == "expectimax" && (options.width, options.height) != (4, 4) {
+        eprintln!("expectimax only plays 4x4 boards");
+        std::process::exit(1);
+    }
+    match &options.evaluator {
+        None if options.strategy == "ntuple" => {
+            eprintln!("ntuple needs trained --weights");
+            std::process::exit(1);
+        }
+        Some(evaluator) => {
+            let network = evaluator.get_network();
+            if (network.get_width(), network.get_height()) != (options.width, options.height) {
+                eprintln!(
+                    "the weights were trained on {}x{} boards",
+                    network.get_width(),
+                    network.get_height()
+                );
+                std::process::exit(1);
+            }
+        }
+        None => {}
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let start = Instant::now();
+    let results = run(&options);
+    let report = report(&options, results, start.elapsed().as_secs_f64());
+    match options.format {
+        Format::Table => print_table(&report),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("a report is always valid JSON")
+        ),
+        Format::Csv => print_csv(&report),
+    }
+}
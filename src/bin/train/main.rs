@@ -0,0 +1,217 @@
+//! Train an n-tuple network by playing against itself.
+//!
+//! Every game is played by the network being trained, picking the move
+//! whose points plus the value of the board after it are the highest, and
+//! each afterstate's value is moved towards the points of the next move
+//! plus the value of the afterstate after it (TD(0)). The weights are saved
+//! every few games, and training picks up where a weights file left off.
+
+use std::path::Path;
+use std::time::Instant;
+
+use rs2048::ai::ntuple::{self, NTupleNetwork};
+use rs2048::ai::strategy;
+use rs2048::game::parse_shape;
+use rs2048::Game;
+
+const USAGE: &str = "usage: rs2048-train --weights FILE [--games N] [--checkpoint N] \
+[--size WIDTHxHEIGHT] [--patterns CELLS] [--alpha RATE] [--seed FIRST]";
+
+/// Tile counted as winning in the progress reports
+const TARGET: usize = 2048;
+
+struct Options {
+    weights: String,
+    /// Games to play in this run
+    games: u64,
+    /// Games between saving the weights
+    checkpoint: u64,
+    /// Board shape and patterns of new networks
+    shape: Option<(usize, usize)>,
+    patterns: Option<Vec<Vec<usize>>>,
+    /// Learning rate
+    alpha: f32,
+    /// Seed of the first game trained on by new networks, kept in the
+    /// weights so resumed runs go on counting up from it
+    first_seed: Option<u64>,
+}
+
+/// How the games since the last checkpoint went
+#[derive(Default)]
+struct Progress {
+    games: u64,
+    score: u64,
+    won: u64,
+    max_tile: usize,
+}
+
+/// Play a game from `seed` to the end, learning from every move
+fn train(network: &mut NTupleNetwork, alpha: f32, seed: u64) -> Game {
+    let mut game = Game::from_shape(network.get_width(), network.get_height(), seed);
+    game.set_history_capacity(0);
+    game.keep_playing();
+    game.seed_cell();
+    game.seed_cell();
+    // The afterstate of the last move
+    let mut previous: Option<Vec<u8>> = None;
+    loop {
+        let score = game.get_score();
+        let best = strategy::legal_moves(&game)
+            .into_iter()
+            .map(|(direction, next)| {
+                let after = ntuple::exponents(&next);
+                let points = (next.get_score() - score) as f32;
+                let value = network.value(&after);
+                (direction, points, after, value)
+            })
+            .rev()
+            .max_by(|a, b| (a.1 + a.3).total_cmp(&(b.1 + b.3)));
+        let (direction, points, after, value) = match best {
+            Some(best) => best,
+            None => break,
+        };
+        if let Some(previous) = previous.take() {
+            let error = points + value - network.value(&previous);
+            network.update(&previous, alpha * error);
+        }
+        game.play(&direction);
+        previous = Some(after);
+    }
+    // Nothing more is scored after the last move
+    if let Some(previous) = previous {
+        let error = -network.value(&previous);
+        network.update(&previous, alpha * error);
+    }
+    network.add_game();
+    game
+}
+
+/// Write the weights to a temporary file first, so a run stopped while
+/// saving keeps the last checkpoint
+fn save(network: &NTupleNetwork, path: &str) {
+    let temporary = format!("{}.tmp", path);
+    if let Err(error) = std::fs::write(&temporary, network.to_bytes())
+        .and_then(|_| std::fs::rename(&temporary, path))
+    {
+        eprintln!("Failed to save weights to {}: {}", path, error);
+        std::process::exit(1);
+    }
+}
+
+/// Resume training from `options.weights`, or start a new network if
+/// there is no such file
+fn load(options: &Options) -> NTupleNetwork {
+    if Path::new(&options.weights).exists() {
+        if options.shape.is_some() || options.patterns.is_some() || options.first_seed.is_some() {
+            eprintln!("--size, --patterns and --seed only apply to new weights");
+            std::process::exit(1);
+        }
+        return match NTupleNetwork::load(&options.weights) {
+            Ok(network) => network,
+            Err(error) => {
+                eprintln!("Failed to load weights from {}: {}", options.weights, error);
+                std::process::exit(1);
+            }
+        };
+    }
+    let (width, height) = options.shape.unwrap_or((4, 4));
+    let patterns = match options.patterns.clone() {
+        Some(patterns) => patterns,
+        None => ntuple::default_patterns(width, height).unwrap_or_else(|| {
+            eprintln!("The default patterns need a board of at least 4x3, pass --patterns");
+            std::process::exit(1);
+        }),
+    };
+    match NTupleNetwork::new(width, height, patterns) {
+        Ok(mut network) => {
+            network.set_first_seed(options.first_seed.unwrap_or(0));
+            network
+        }
+        Err(error) => {
+            eprintln!("Failed to create weights: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut weights = None;
+    let mut options = Options {
+        weights: String::new(),
+        games: 100_000,
+        checkpoint: 1000,
+        shape: None,
+        patterns: None,
+        alpha: 0.1,
+        first_seed: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let argument = args.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "--weights" => weights = Some(argument),
+            "--games" => options.games = argument.parse().unwrap_or_else(|_| usage()),
+            "--checkpoint" => {
+                options.checkpoint = match argument.parse() {
+                    Ok(checkpoint) if checkpoint > 0 => checkpoint,
+                    _ => usage(),
+                }
+            }
+            "--size" => options.shape = Some(parse_shape(&argument).unwrap_or_else(|| usage())),
+            "--patterns" => {
+                options.patterns =
+                    Some(ntuple::parse_patterns(&argument).unwrap_or_else(|| usage()))
+            }
+            "--alpha" => {
+                options.alpha = match argument.parse() {
+                    Ok(alpha) if alpha > 0.0 => alpha,
+                    _ => usage(),
+                }
+            }
+            "--seed" => options.first_seed = Some(argument.parse().unwrap_or_else(|_| usage())),
+            _ => usage(),
+        }
+    }
+    options.weights = weights.unwrap_or_else(|| usage());
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let mut network = load(&options);
+    if network.get_games() > 0 {
+        println!("Resuming after {} games", network.get_games());
+    }
+
+    let mut progress = Progress::default();
+    let mut since = Instant::now();
+    for played in 1..=options.games {
+        let seed = network.get_first_seed().wrapping_add(network.get_games());
+        let game = train(&mut network, options.alpha, seed);
+        progress.games += 1;
+        progress.score += game.get_score() as u64;
+        progress.won += u64::from(game.get_max_tile() >= TARGET);
+        progress.max_tile = progress.max_tile.max(game.get_max_tile());
+
+        if played % options.checkpoint == 0 || played == options.games {
+            save(&network, &options.weights);
+            let seconds = since.elapsed().as_secs_f64();
+            println!(
+                "{:>10} games  average score {:>8.0}  {} in {:>5.1}%  max tile {:>6}  {:.1} games/s",
+                network.get_games(),
+                progress.score as f64 / progress.games as f64,
+                TARGET,
+                100.0 * progress.won as f64 / progress.games as f64,
+                progress.max_tile,
+                progress.games as f64 / seconds.max(f64::EPSILON)
+            );
+            progress = Progress::default();
+            since = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Play rs2048 in the terminal.
+//!
+//! Every game is recorded, finished games are saved as replays in the data
+//! directory. Run with `--replay <file>` (or the replay's base64 text) to
+//! watch one.
+//!
+//! T switches between the built-in themes and any custom ones kept in the
+//! data directory's `themes` folder, `--theme` picks one by name or loads
+//! a new one from a JSON file.
+//!
+//! Every game that ends or is won goes into the high score table and the
+//! statistics, `--scores` and `--stats` print them. Games reset or quit
+//! halfway don't count.
+//!
+//! P lets the computer play with the strategy `--strategy` names and ?
+//! asks for a hint. Both can use n-tuple network weights trained by
+//! `rs2048-train` and passed with `--weights`.
+
+use std::io::{stdout, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use termion::color::{Bg, Fg, Reset, Rgb};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{clear, cursor};
+
+use rs2048::ai::strategy::{self, Strategy, STRATEGIES};
+use rs2048::ai::{Evaluator, Expectimax, MonteCarlo, NTupleNetwork};
+use rs2048::game::parse_shape;
+use rs2048::replay::Recorder;
+use rs2048::scores::{HighScores, Score, Variant};
+use rs2048::stats::{format_duration, GameRecord, Stats};
+use rs2048::theme::{self, Theme};
+use rs2048::{Direction, Game};
+
+mod replay;
+mod storage;
+
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 3;
+const HELP: &str =
+    "arrows/wasd/hjkl: move  u: undo  r: reset  t: theme  p: autoplay  ?: hint  q: quit";
+const USAGE: &str = "usage: rs2048-tui [--size WIDTHxHEIGHT] [--theme NAME|FILE] \
+[--strategy expectimax|montecarlo|ntuple|snake|corner|greedy|random] [--weights FILE] \
+[--replay FILE] [--scores] [--stats]";
+/// Delay between moves while the computer plays
+const AUTOPLAY_MS: u64 = 150;
+/// How often keys are checked for
+const POLL_MS: u64 = 10;
+/// Milliseconds Monte Carlo spends on a hint without weights
+const HINT_MS: f64 = 200.0;
+/// Length of the longest bar in the score histogram of `--stats`
+const STATS_BAR_WIDTH: usize = 40;
+
+/// Turn a colour like "#eee4da" from a theme into a terminal colour
+fn rgb(hex: &str) -> Rgb {
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    Rgb(channel(1), channel(3), channel(5))
+}
+
+/// The current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a time in seconds since the Unix epoch as a date like 2024-03-01
+fn format_date(seconds: u64) -> String {
+    // Howard Hinnant's days_from_civil, backwards
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Print the high score table of every variant played
+fn print_scores(scores: &HighScores) {
+    let mut variants = scores.variants().peekable();
+    if variants.peek().is_none() {
+        println!("No records yet");
+    }
+    for variant in variants {
+        println!("{}", variant.name());
+        for (rank, score) in scores.top(variant).iter().enumerate() {
+            println!(
+                "{:>4}. {:>8}  max tile {:>6}  {:>6} moves  {}",
+                rank + 1,
+                score.score,
+                score.max_tile,
+                score.moves,
+                format_date(score.date)
+            );
+        }
+    }
+}
+
+/// Print what all games played add up to
+fn print_stats(stats: &Stats) {
+    let summary = stats.summary(None);
+    if summary.games == 0 {
+        println!("No games played yet");
+        return;
+    }
+    println!("Games played   {:>8}", summary.games);
+    println!("Average score  {:>8.0}", summary.average_score);
+    println!("Median score   {:>8.0}", summary.median_score);
+    println!("Best score     {:>8}", summary.best_score);
+    println!(
+        "Time played    {:>8}",
+        format_duration(summary.total_duration)
+    );
+    println!(
+        "Win streak     {:>8}  (longest {})",
+        summary.current_streak, summary.longest_streak
+    );
+
+    // Start at the largest tile every game reached
+    let start = summary
+        .reached
+        .iter()
+        .rposition(|&(_, count)| count == summary.games)
+        .unwrap_or(0);
+    println!("\nTiles reached");
+    for &(tile, count) in &summary.reached[start..] {
+        println!(
+            "{:>8}  {:>5.1}%",
+            tile,
+            100.0 * count as f64 / summary.games as f64
+        );
+    }
+
+    println!("\nScores");
+    let most = summary
+        .histogram
+        .iter()
+        .map(|&(_, count)| count)
+        .max()
+        .unwrap_or(1);
+    for &(from, count) in &summary.histogram {
+        let bar = "#".repeat((count * STATS_BAR_WIDTH).div_ceil(most));
+        println!("{:>8} - {:<8} {:>5}  {}", from, 2 * from - 1, count, bar);
+    }
+}
+
+/// Enter the result of a game started at `started` in the high score
+/// table and the statistics, returns what was entered
+fn submit(
+    game: &Game,
+    started: u64,
+    scores: &mut HighScores,
+    stats: &mut Stats,
+) -> (Score, GameRecord) {
+    let now = now();
+    let score = Score::of(game, now);
+    scores.submit(Variant::of(game), score.clone());
+    storage::save_scores(scores);
+    let record = GameRecord::of(game, now.saturating_sub(started), now)
+        .expect("only games with a result are submitted");
+    stats.record(record.clone());
+    storage::save_stats(stats);
+    (score, record)
+}
+
+/// Take back a result entered by `submit`, once its game is undone
+fn withdraw(submitted: &(Score, GameRecord), scores: &mut HighScores, stats: &mut Stats) {
+    let (score, record) = submitted;
+    scores.withdraw(&record.variant, score);
+    storage::save_scores(scores);
+    stats.withdraw(record);
+    storage::save_stats(stats);
+}
+
+fn get_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Draw the scores, the board in `theme` and any message below it,
+/// `status` replaces the messages and help of a game being played
+fn draw(
+    out: &mut impl Write,
+    game: &Game,
+    theme: &Theme,
+    status: Option<&str>,
+) -> std::io::Result<()> {
+    write!(out, "{}{}", clear::All, cursor::Goto(1, 1))?;
+    write!(
+        out,
+        "Score: {:<8} Best: {}\r\n\r\n",
+        game.get_score(),
+        game.get_best()
+    )?;
+
+    let board = Bg(rgb(&theme.board));
+    let gap = " ".repeat(game.get_width() * (CELL_WIDTH + 1) + 1);
+    write!(out, "{}{}{}\r\n", board, gap, Bg(Reset))?;
+    for y in 0..game.get_height() {
+        for line in 0..CELL_HEIGHT {
+            write!(out, "{} ", board)?;
+            for x in 0..game.get_width() {
+                let value = game.get_state(x, y);
+                let style = theme.tile(value);
+                let text = match value {
+                    _ if value != 0 && line == CELL_HEIGHT / 2 => value.to_string(),
+                    _ => String::new(),
+                };
+                write!(
+                    out,
+                    "{}{}{:^width$}{} ",
+                    Bg(rgb(&style.background)),
+                    Fg(rgb(&style.foreground)),
+                    text,
+                    board,
+                    width = CELL_WIDTH
+                )?;
+            }
+            write!(out, "{}{}\r\n", Bg(Reset), Fg(Reset))?;
+        }
+        write!(out, "{}{}{}\r\n", board, gap, Bg(Reset))?;
+    }
+
+    write!(out, "\r\n")?;
+    if let Some(status) = status {
+        write!(out, "{}\r\n", status)?;
+        return out.flush();
+    }
+    if game.is_won() && !game.is_keep_playing() {
+        write!(out, "You win! Press c to keep going.\r\n")?;
+    } else if game.is_finished() {
+        write!(out, "Game over! Press r to try again.\r\n")?;
+    }
+    write!(out, "{}\r\n", HELP)?;
+    out.flush()
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+/// Pick the theme `argument` names, or load it from the JSON file it
+/// points to and keep it for later
+fn pick_theme(themes: &mut Vec<Theme>, argument: &str) -> Option<usize> {
+    if let Some(index) = themes.iter().position(|theme| theme.name == argument) {
+        return Some(index);
+    }
+    let json = std::fs::read_to_string(argument).ok()?;
+    match Theme::from_json(&json) {
+        Ok(theme) => {
+            storage::save_theme(&theme);
+            let name = theme.name.clone();
+            theme::add_theme(themes, theme);
+            themes.iter().position(|theme| theme.name == name)
+        }
+        Err(error) => {
+            eprintln!("Failed to load theme: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let mut shape = (4, 4);
+    let mut strategy_name = STRATEGIES[0].to_string();
+    let mut evaluator = None;
+    let mut themes = storage::load_themes();
+    // The theme picked last time, or the classic one
+    let mut theme = storage::load_theme_name()
+        .and_then(|name| themes.iter().position(|theme| theme.name == name))
+        .unwrap_or(0);
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--scores" {
+            print_scores(&storage::load_scores());
+            return Ok(());
+        }
+        if flag == "--stats" {
+            print_stats(&storage::load_stats());
+            return Ok(());
+        }
+        match (flag.as_str(), args.next()) {
+            ("--size", Some(argument)) => {
+                shape = parse_shape(&argument).unwrap_or_else(|| usage());
+            }
+            ("--strategy", Some(argument))
+                if STRATEGIES.contains(&argument.as_str()) || argument == "ntuple" =>
+            {
+                strategy_name = argument;
+            }
+            ("--weights", Some(argument)) => {
+                evaluator = match NTupleNetwork::load(&argument) {
+                    Ok(network) => Some(Evaluator::new(network)),
+                    Err(error) => {
+                        eprintln!("Failed to load weights from {}: {}", argument, error);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            ("--theme", Some(argument)) => {
+                theme = pick_theme(&mut themes, &argument).unwrap_or_else(|| usage());
+                storage::save_theme_name(&themes[theme].name);
+            }
+            ("--replay", Some(argument)) => {
+                let replay = match replay::load(&argument) {
+                    Ok(replay) => replay,
+                    Err(error) => {
+                        eprintln!("Failed to load replay: {}", error);
+                        std::process::exit(1);
+                    }
+                };
+                let mut out = stdout().into_raw_mode()?.into_alternate_screen()?;
+                write!(out, "{}", cursor::Hide)?;
+                let result = replay::run(&mut out, replay, &themes[theme]);
+                write!(out, "{}", cursor::Show)?;
+                return result;
+            }
+            _ => usage(),
+        }
+    }
+
+    let (width, height) = shape;
+    if let Some(evaluator) = &evaluator {
+        let network = evaluator.get_network();
+        if (network.get_width(), network.get_height()) != shape {
+            eprintln!(
+                "The weights were trained on {}x{} boards",
+                network.get_width(),
+                network.get_height()
+            );
+            std::process::exit(1);
+        }
+    }
+    let mut strategy: Box<dyn Strategy> = match (strategy_name.as_str(), &evaluator) {
+        ("ntuple", Some(evaluator)) => Box::new(evaluator.clone()),
+        ("ntuple", None) => {
+            eprintln!("ntuple needs trained --weights");
+            std::process::exit(1);
+        }
+        // The search values boards with the weights instead of its own
+        // heuristic
+        ("expectimax", Some(evaluator)) => match evaluator.heuristic() {
+            Ok(heuristic) => Box::new(Expectimax::with_heuristic(
+                Expectimax::default().get_depth(),
+                Box::new(heuristic),
+            )),
+            Err(error) => {
+                eprintln!("Failed to use the weights: {}", error);
+                std::process::exit(1);
+            }
+        },
+        (name, _) => {
+            strategy::named(name, get_seed()).expect("strategies are checked when parsing")
+        }
+    };
+    // Hints come from the weights if there are any, random playouts
+    // otherwise
+    let mut hints: Box<dyn Strategy> = match evaluator {
+        Some(evaluator) => Box::new(evaluator),
+        None => {
+            let mut montecarlo = MonteCarlo::new(usize::MAX, get_seed());
+            montecarlo.set_budget(Some(HINT_MS));
+            Box::new(montecarlo)
+        }
+    };
+    let mut scores = storage::load_scores();
+    let mut stats = storage::load_stats();
+    let mut game = Game::from_shape(width, height, get_seed());
+    // Best scores stored before the table existed still count
+    let old_best = storage::load_best(width, height).unwrap_or(0);
+    game.set_best(scores.best(&Variant::of(&game)).max(old_best));
+    game.seed_cell();
+    // The game's result once it has been entered in the high score table
+    let mut submitted = None;
+    let mut started = now();
+    let mut recorder = Recorder::new(&game);
+    let mut autoplay = false;
+    let mut last_move = Instant::now();
+
+    let mut out = stdout().into_raw_mode()?.into_alternate_screen()?;
+    write!(out, "{}", cursor::Hide)?;
+    draw(&mut out, &game, &themes[theme], None)?;
+
+    let mut keys = termion::async_stdin().keys();
+    loop {
+        let key = match keys.next() {
+            Some(key) => Some(key?),
+            None => {
+                thread::sleep(Duration::from_millis(POLL_MS));
+                None
+            }
+        };
+        let mut hint = None;
+        let direction = match key {
+            None if autoplay && last_move.elapsed() >= Duration::from_millis(AUTOPLAY_MS) => {
+                last_move = Instant::now();
+                let direction = match game.accepts_moves() {
+                    true => strategy.next_move(&game),
+                    false => None,
+                };
+                // Stop once the game ends or the strategy is stuck
+                autoplay = direction.is_some();
+                direction
+            }
+            None => continue,
+            Some(key) => match key {
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => break,
+                Key::Char('r') => {
+                    storage::save_replay(recorder.get_replay());
+                    // Games abandoned halfway aren't counted
+                    if submitted.take().is_none() && game.has_result() {
+                        submit(&game, started, &mut scores, &mut stats);
+                    }
+                    started = now();
+                    game.clear();
+                    game.reseed(get_seed());
+                    game.seed_cell();
+                    recorder = Recorder::new(&game);
+                    None
+                }
+                Key::Char('u') | Key::Ctrl('z') => {
+                    if game.undo() {
+                        recorder.undo();
+                        // The game goes on, its result is entered again once
+                        // it really ends
+                        if let Some(submitted) = submitted.take() {
+                            withdraw(&submitted, &mut scores, &mut stats);
+                        }
+                    }
+                    None
+                }
+                Key::Char('t') => {
+                    theme = (theme + 1) % themes.len();
+                    storage::save_theme_name(&themes[theme].name);
+                    None
+                }
+                Key::Char('p') => {
+                    autoplay = !autoplay;
+                    None
+                }
+                Key::Char('?') => {
+                    hint = hints.next_move(&game);
+                    None
+                }
+                Key::Char('c') if game.is_won() && !game.is_keep_playing() => {
+                    game.keep_playing();
+                    None
+                }
+                Key::Up | Key::Char('w') | Key::Char('k') => Some(Direction::Up),
+                Key::Down | Key::Char('s') | Key::Char('j') => Some(Direction::Down),
+                Key::Left | Key::Char('a') | Key::Char('h') => Some(Direction::Left),
+                Key::Right | Key::Char('d') | Key::Char('l') => Some(Direction::Right),
+                _ => None,
+            },
+        };
+        if let Some(direction) = direction {
+            // Ignore moves while the game is over or won
+            if game.accepts_moves() {
+                let outcome = game.play(&direction);
+                recorder.record(direction, &outcome);
+                if game.is_finished() && submitted.is_none() {
+                    submitted = Some(submit(&game, started, &mut scores, &mut stats));
+                }
+            }
+        }
+        let status = hint.map(|direction| format!("Hint: {:?}\r\n{}", direction, HELP));
+        draw(&mut out, &game, &themes[theme], status.as_deref())?;
+    }
+
+    write!(out, "{}", cursor::Show)?;
+    storage::save_replay(recorder.get_replay());
+    if submitted.is_none() && game.has_result() {
+        submit(&game, started, &mut scores, &mut stats);
+    }
+    Ok(())
+}
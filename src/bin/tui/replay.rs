@@ -0,0 +1,108 @@
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion::event::Key;
+use termion::input::TermRead;
+
+use rs2048::replay::{Player, Replay};
+use rs2048::theme::Theme;
+
+use crate::draw;
+
+/// Delays between moves while playing, slowest first
+const SPEEDS: [u64; 5] = [1000, 500, 250, 100, 25];
+/// How often keys are checked for while playing
+const POLL_MS: u64 = 10;
+const HELP: &str = "space: play/pause  left/right: step  +/-: speed  home/end: jump  q: quit";
+
+/// Read a replay from a file, or from the argument itself if it isn't a
+/// file but could be a replay in base64
+pub fn load(argument: &str) -> std::io::Result<Replay> {
+    let encoded = match std::fs::read_to_string(argument) {
+        Ok(encoded) => encoded,
+        Err(_) if is_base64(argument) => argument.to_owned(),
+        Err(error) => return Err(error),
+    };
+    Replay::from_base64(encoded.trim())
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+}
+
+/// Whether `text` only holds letters of URL-safe base64, unlike paths with
+/// slashes or dots
+fn is_base64(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Play `replay` back in `theme` until the user quits
+pub fn run(out: &mut impl Write, replay: Replay, theme: &Theme) -> std::io::Result<()> {
+    let mut player = match Player::new(replay) {
+        Ok(player) => player,
+        Err(error) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
+    };
+    let mut keys = termion::async_stdin().keys();
+    let mut playing = true;
+    let mut speed = 2;
+    let mut last_move = Instant::now();
+
+    loop {
+        let status = format!(
+            "Move {}/{}  {}  speed {}/{}\r\n{}",
+            player.get_index(),
+            player.len(),
+            if playing { "playing" } else { "paused" },
+            speed + 1,
+            SPEEDS.len(),
+            HELP
+        );
+        draw(out, player.get_game(), theme, Some(&status))?;
+
+        // Wait for a key, or until the next move is due
+        let mut redraw = false;
+        while !redraw {
+            match keys.next() {
+                Some(key) => {
+                    match key? {
+                        Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(()),
+                        Key::Char(' ') => {
+                            // Playing from the end starts over
+                            if !playing && player.is_at_end() {
+                                player.seek(0);
+                            }
+                            playing = !playing;
+                        }
+                        Key::Right | Key::Char('l') => {
+                            playing = false;
+                            player.step_forward();
+                        }
+                        Key::Left | Key::Char('h') => {
+                            playing = false;
+                            player.step_back();
+                        }
+                        Key::Home => player.seek(0),
+                        Key::End => player.seek(player.len()),
+                        Key::Char('+') | Key::Char('=') => {
+                            speed = (speed + 1).min(SPEEDS.len() - 1)
+                        }
+                        Key::Char('-') => speed = speed.saturating_sub(1),
+                        _ => continue,
+                    }
+                    last_move = Instant::now();
+                    redraw = true;
+                }
+                None if playing => {
+                    if last_move.elapsed() >= Duration::from_millis(SPEEDS[speed]) {
+                        player.step_forward();
+                        playing = !player.is_at_end();
+                        last_move = Instant::now();
+                        redraw = true;
+                    } else {
+                        thread::sleep(Duration::from_millis(POLL_MS));
+                    }
+                }
+                None => thread::sleep(Duration::from_millis(POLL_MS)),
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rs2048::replay::Replay;
+use rs2048::scores::HighScores;
+use rs2048::stats::Stats;
+use rs2048::theme::{self, Theme};
+
+/// Directory for rs2048's files, following the XDG base directory spec
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("rs2048"))
+}
+
+/// File holding the best score on boards of a shape, 4x4 keeps the name
+/// it had before other shapes could be played
+fn best_file(width: usize, height: usize) -> String {
+    match (width, height) {
+        (4, 4) => "best".to_owned(),
+        _ => format!("best-{}x{}", width, height),
+    }
+}
+
+/// Recover the best score on boards of a shape stored before there was a
+/// high score table
+pub fn load_best(width: usize, height: usize) -> Option<usize> {
+    let path = data_dir()?.join(best_file(width, height));
+    fs::read_to_string(path).ok()?.trim().parse::<usize>().ok()
+}
+
+/// Store a finished game's replay in the data directory's `replays`
+/// folder, named after the time it was saved
+pub fn save_replay(replay: &Replay) {
+    if replay.moves.is_empty() {
+        return;
+    }
+    let dir = match data_dir() {
+        Some(dir) => dir.join("replays"),
+        None => return,
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("{}.replay", timestamp));
+    if fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, format!("{}\n", replay.to_base64())))
+        .is_err()
+    {
+        eprintln!("Failed to save replay to {}!", path.display());
+    }
+}
+
+/// Recover the high score table from the data directory, an empty table
+/// is used if there is none or it can't be read
+pub fn load_scores() -> HighScores {
+    let json = match data_dir().and_then(|dir| fs::read_to_string(dir.join("scores.json")).ok()) {
+        Some(json) => json,
+        None => return HighScores::default(),
+    };
+    HighScores::from_json(&json).unwrap_or_else(|error| {
+        eprintln!("Failed to load high scores: {}", error);
+        HighScores::default()
+    })
+}
+
+/// Store the high score table in the data directory
+pub fn save_scores(scores: &HighScores) {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    if fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join("scores.json"), scores.to_json()))
+        .is_err()
+    {
+        eprintln!("Failed to save high scores to {}!", dir.display());
+    }
+}
+
+/// The built-in themes followed by the custom ones in the data directory's
+/// `themes` folder, in the order of their file names
+pub fn load_themes() -> Vec<Theme> {
+    let mut themes = Theme::builtin();
+    let entries = match data_dir().and_then(|dir| fs::read_dir(dir.join("themes")).ok()) {
+        Some(entries) => entries,
+        None => return themes,
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+    for path in paths {
+        match fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|json| Theme::from_json(&json).map_err(|error| error.to_string()))
+        {
+            Ok(theme) => theme::add_theme(&mut themes, theme),
+            Err(error) => eprintln!("Failed to load theme {}: {}", path.display(), error),
+        }
+    }
+    themes
+}
+
+/// Keep a custom theme in the data directory so it can be picked again
+pub fn save_theme(theme: &Theme) {
+    let dir = match data_dir() {
+        Some(dir) => dir.join("themes"),
+        None => return,
+    };
+    // Names can hold anything, file names can't
+    let file: String = theme
+        .name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let path = dir.join(format!("{}.json", file));
+    if fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, theme.to_json()))
+        .is_err()
+    {
+        eprintln!("Failed to save theme to {}!", path.display());
+    }
+}
+
+/// Name of the theme picked last time
+pub fn load_theme_name() -> Option<String> {
+    let name = fs::read_to_string(data_dir()?.join("theme")).ok()?;
+    Some(name.trim().to_owned())
+}
+
+/// Remember the theme picked for next time
+pub fn save_theme_name(name: &str) {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    if fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join("theme"), format!("{}\n", name)))
+        .is_err()
+    {
+        eprintln!("Failed to save theme choice to {}!", dir.display());
+    }
+}
+
+/// Recover the statistics from the data directory, starting over if there
+/// are none or they can't be read
+pub fn load_stats() -> Stats {
+    let json = match data_dir().and_then(|dir| fs::read_to_string(dir.join("stats.json")).ok()) {
+        Some(json) => json,
+        None => return Stats::default(),
+    };
+    Stats::from_json(&json).unwrap_or_else(|error| {
+        eprintln!("Failed to load statistics: {}", error);
+        Stats::default()
+    })
+}
+
+/// Store the statistics in the data directory
+pub fn save_stats(stats: &Stats) {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    if fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join("stats.json"), stats.to_json()))
+        .is_err()
+    {
+        eprintln!("Failed to save statistics to {}!", dir.display());
+    }
+}
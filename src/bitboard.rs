@@ -0,0 +1,315 @@
+//! A packed 4x4 board for fast moves.
+//!
+//! Each cell is stored as a 4 bit exponent in a single `u64`, with 0 for an
+//! empty cell and `k` for a tile of `2^k`. Cell (x,y) lives in nibble
+//! `4 * y + x`, so every row is one `u16`. Moving a row is a lookup in a
+//! table that is computed once for all 65536 rows; columns are moved by
+//! transposing the board first. This makes a move a handful of lookups
+//! instead of a walk over every tile, which is what the AI and simulations
+//! need.
+//!
+//! Tiles up to 32768 can be represented. Two 32768 tiles are never merged
+//! since their sum doesn't fit into a nibble, so boards holding more than
+//! one of them aren't packed at all. Every board that is packed moves
+//! exactly like `Game::step`, only boards reached by moving it further may
+//! end up with two 32768 tiles that stay apart.
+
+use std::sync::OnceLock;
+
+use crate::game::{Direction, Game};
+
+const SIZE: usize = 4;
+const ROW_MASK: u64 = 0xffff;
+const MAX_EXPONENT: u8 = 15;
+
+/// Precomputed results of moving every possible row
+struct Tables {
+    left: Vec<u16>,
+    right: Vec<u16>,
+    score_left: Vec<u32>,
+    score_right: Vec<u32>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let mut tables = Tables {
+            left: vec![0; 1 << 16],
+            right: vec![0; 1 << 16],
+            score_left: vec![0; 1 << 16],
+            score_right: vec![0; 1 << 16],
+        };
+        for row in 0..=u16::MAX {
+            let cells = unpack_row(row);
+            let (left, score_left) = move_row_left(cells);
+            tables.left[row as usize] = pack_row(left);
+            tables.score_left[row as usize] = score_left;
+
+            let mut reversed = cells;
+            reversed.reverse();
+            let (mut right, score_right) = move_row_left(reversed);
+            right.reverse();
+            tables.right[row as usize] = pack_row(right);
+            tables.score_right[row as usize] = score_right;
+        }
+        tables
+    })
+}
+
+fn unpack_row(row: u16) -> [u8; SIZE] {
+    let mut cells = [0; SIZE];
+    for (i, cell) in cells.iter_mut().enumerate() {
+        *cell = ((row >> (4 * i)) & 0xf) as u8;
+    }
+    cells
+}
+
+fn pack_row(cells: [u8; SIZE]) -> u16 {
+    cells
+        .iter()
+        .enumerate()
+        .fold(0, |row, (i, &cell)| row | (u16::from(cell) << (4 * i)))
+}
+
+/// Slide and merge a single row towards index 0, returning the points scored
+fn move_row_left(cells: [u8; SIZE]) -> ([u8; SIZE], u32) {
+    let mut result = [0; SIZE];
+    let mut score = 0;
+    let mut target = 0;
+    let mut mergeable = false;
+    for &cell in cells.iter().filter(|&&cell| cell != 0) {
+        if mergeable && result[target - 1] == cell && cell < MAX_EXPONENT {
+            result[target - 1] += 1;
+            score += 1 << result[target - 1];
+            mergeable = false;
+        } else {
+            result[target] = cell;
+            target += 1;
+            mergeable = true;
+        }
+    }
+    (result, score)
+}
+
+/// Swap rows and columns
+fn transpose(board: u64) -> u64 {
+    let a1 = board & 0xf0f0_0f0f_f0f0_0f0f;
+    let a2 = board & 0x0000_f0f0_0000_f0f0;
+    let a3 = board & 0x0f0f_0000_0f0f_0000;
+    let a = a1 | (a2 << 12) | (a3 >> 12);
+    let b1 = a & 0xff00_ff00_00ff_00ff;
+    let b2 = a & 0x00ff_00ff_0000_0000;
+    let b3 = a & 0x0000_0000_ff00_ff00;
+    b1 | (b2 >> 24) | (b3 << 24)
+}
+
+/// Apply a row table to all four rows
+fn map_rows(board: u64, table: &[u16], scores: &[u32]) -> (u64, u32) {
+    let mut result = 0;
+    let mut score = 0;
+    for y in 0..SIZE {
+        let row = ((board >> (16 * y)) & ROW_MASK) as usize;
+        result |= u64::from(table[row]) << (16 * y);
+        score += scores[row];
+    }
+    (result, score)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    /// Pack a row-major list of tile values, as returned by
+    /// `Game::get_states`
+    ///
+    /// Returns `None` unless there are exactly 16 cells holding powers of
+    /// two up to 32768, with at most one 32768 since two of them wouldn't
+    /// merge like they do in `Game`.
+    pub fn from_states(states: &[usize]) -> Option<Self> {
+        if states.len() != SIZE * SIZE {
+            return None;
+        }
+        if states
+            .iter()
+            .filter(|&&value| value == 1 << MAX_EXPONENT)
+            .count()
+            > 1
+        {
+            return None;
+        }
+        let mut board = 0;
+        for (i, &value) in states.iter().enumerate() {
+            let exponent = match value {
+                0 => 0,
+                _ if value.is_power_of_two() && value > 1 => value.trailing_zeros() as u64,
+                _ => return None,
+            };
+            if exponent > u64::from(MAX_EXPONENT) {
+                return None;
+            }
+            board |= exponent << (4 * i);
+        }
+        Some(Bitboard(board))
+    }
+
+    /// Pack the board of a 4x4 game, see `from_states`
+    pub fn from_game(game: &Game) -> Option<Self> {
+        if game.get_width() != SIZE || game.get_height() != SIZE {
+            return None;
+        }
+        Bitboard::from_states(&game.get_states())
+    }
+
+    /// Unpack into a row-major list of tile values
+    pub fn to_states(self) -> Vec<usize> {
+        (0..SIZE * SIZE).map(|i| self.value_at(i)).collect()
+    }
+
+    /// Tile value at (x,y), 0 for an empty cell
+    pub fn get_state(self, x: usize, y: usize) -> usize {
+        self.value_at(y * SIZE + x)
+    }
+
+    /// Exponent at (x,y), 0 for an empty cell
+    pub fn get_exponent(self, x: usize, y: usize) -> u8 {
+        self.exponent_at(y * SIZE + x)
+    }
+
+    /// Return a copy with the exponent of cell `index` (`4 * y + x`) set
+    pub fn with_exponent(self, index: usize, exponent: u8) -> Self {
+        let shift = 4 * index;
+        Bitboard((self.0 & !(0xf << shift)) | (u64::from(exponent & 0xf) << shift))
+    }
+
+    /// Move all tiles in `direction`, returning the new board and the
+    /// points scored
+    ///
+    /// The board is unchanged if the move is illegal.
+    pub fn shift(self, direction: Direction) -> (Self, u32) {
+        let tables = tables();
+        let (board, score) = match direction {
+            Direction::Left => map_rows(self.0, &tables.left, &tables.score_left),
+            Direction::Right => map_rows(self.0, &tables.right, &tables.score_right),
+            Direction::Up => {
+                let (board, score) = map_rows(transpose(self.0), &tables.left, &tables.score_left);
+                (transpose(board), score)
+            }
+            Direction::Down => {
+                let (board, score) =
+                    map_rows(transpose(self.0), &tables.right, &tables.score_right);
+                (transpose(board), score)
+            }
+        };
+        (Bitboard(board), score)
+    }
+
+    /// Exponents of row `y`, starting at x = 0
+    pub fn row(self, y: usize) -> [u8; SIZE] {
+        unpack_row(((self.0 >> (16 * y)) & ROW_MASK) as u16)
+    }
+
+    /// Swap rows and columns, so columns can be inspected as rows
+    pub fn transpose(self) -> Self {
+        Bitboard(transpose(self.0))
+    }
+
+    /// Check whether there is an empty cell or a pair of equal neighbours
+    pub fn has_moves(self) -> bool {
+        Direction::ALL
+            .iter()
+            .any(|&direction| self.shift(direction).0 != self)
+    }
+
+    /// Indices (`4 * y + x`) of all empty cells
+    pub fn empty_cells(self) -> impl Iterator<Item = usize> {
+        (0..SIZE * SIZE).filter(move |&i| self.exponent_at(i) == 0)
+    }
+
+    pub fn count_empty(self) -> usize {
+        self.empty_cells().count()
+    }
+
+    /// Value of the highest tile on the board
+    pub fn max_tile(self) -> usize {
+        match (0..SIZE * SIZE).map(|i| self.exponent_at(i)).max() {
+            Some(0) | None => 0,
+            Some(exponent) => 1 << exponent,
+        }
+    }
+
+    fn exponent_at(self, index: usize) -> u8 {
+        ((self.0 >> (4 * index)) & 0xf) as u8
+    }
+
+    fn value_at(self, index: usize) -> usize {
+        match self.exponent_at(index) {
+            0 => 0,
+            exponent => 1 << exponent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Rng, SplitMix64};
+
+    /// A random board with at most one 32768 tile
+    fn random_states(rng: &mut SplitMix64) -> Vec<usize> {
+        let mut largest = false;
+        (0..SIZE * SIZE)
+            .map(|_| {
+                // Mostly small tiles, so there is something to merge
+                let mut exponent = match rng.below(4) {
+                    0 => 0,
+                    1 => rng.below(4) as u8 + 1,
+                    _ => rng.below(usize::from(MAX_EXPONENT) + 1) as u8,
+                };
+                if exponent == MAX_EXPONENT {
+                    if largest {
+                        exponent -= 1;
+                    }
+                    largest = true;
+                }
+                match exponent {
+                    0 => 0,
+                    _ => 1 << exponent,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn moves_like_game() {
+        let mut rng = SplitMix64::from_seed(2048);
+        for _ in 0..20_000 {
+            let states = random_states(&mut rng);
+            let board = Bitboard::from_states(&states).unwrap();
+            assert_eq!(board.to_states(), states);
+            for &direction in Direction::ALL.iter() {
+                let mut game = Game::from_shape(SIZE, SIZE, 0);
+                game.set_states(states.clone()).unwrap();
+                let outcome = game.shift(&direction);
+                let (moved, score) = board.shift(direction);
+                assert_eq!(moved.to_states(), game.get_states(), "{:?}", direction);
+                assert_eq!(score as usize, outcome.score_delta);
+                assert_eq!(moved != board, outcome.moved());
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_tiles_that_would_merge_differently() {
+        let mut states = vec![0; SIZE * SIZE];
+        states[0] = 32768;
+        assert!(Bitboard::from_states(&states).is_some());
+        states[1] = 32768;
+        assert!(Bitboard::from_states(&states).is_none());
+        states[1] = 65536;
+        assert!(Bitboard::from_states(&states).is_none());
+        states[1] = 3;
+        assert!(Bitboard::from_states(&states).is_none());
+        assert!(Bitboard::from_states(&states[1..]).is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! Low level helpers for the compact binary save format: LEB128 varints
+//! and URL-safe base64 without padding.
+
+use crate::save::LoadError;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Append `value` in 7 bit groups, least significant first
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads values from the front of a byte slice
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, LoadError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(LoadError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    pub fn read_varint(&mut self) -> Result<u64, LoadError> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(LoadError::Overflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(LoadError::Overflow)
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, LoadError> {
+        let value = self.read_varint()?;
+        if value > usize::MAX as u64 {
+            return Err(LoadError::Overflow);
+        }
+        Ok(value as usize)
+    }
+
+    /// Fail unless every byte has been read
+    pub fn finish(self) -> Result<(), LoadError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            _ => Err(LoadError::TrailingData),
+        }
+    }
+}
+
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+pub fn from_base64(encoded: &str) -> Result<Vec<u8>, LoadError> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return Err(LoadError::InvalidBase64);
+        }
+        let mut group = 0u32;
+        for (i, &symbol) in chunk.iter().enumerate() {
+            let value = ALPHABET
+                .iter()
+                .position(|&letter| letter == symbol)
+                .ok_or(LoadError::InvalidBase64)?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints_round_trip() {
+        let values = [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX];
+        let mut bytes = Vec::new();
+        for &value in &values {
+            write_varint(&mut bytes, value);
+        }
+        let mut reader = Reader::new(&bytes);
+        for &value in &values {
+            assert_eq!(reader.read_varint().unwrap(), value);
+        }
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn rejects_overlong_varints() {
+        let bytes = [0xff; 10];
+        assert!(matches!(
+            Reader::new(&bytes).read_varint(),
+            Err(LoadError::Overflow)
+        ));
+        assert!(matches!(
+            Reader::new(&[0x80]).read_varint(),
+            Err(LoadError::UnexpectedEnd)
+        ));
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for end in 0..bytes.len() {
+            let encoded = to_base64(&bytes[..end]);
+            assert!(!encoded.contains('='));
+            assert_eq!(from_base64(&encoded).unwrap(), &bytes[..end]);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_base64() {
+        assert!(matches!(from_base64("AB+C"), Err(LoadError::InvalidBase64)));
+        assert!(matches!(
+            from_base64("ABCDE"),
+            Err(LoadError::InvalidBase64)
+        ));
+    }
+}
@@ -1,11 +1,35 @@
-#![allow(unused)]
 use std::collections::HashSet;
-use stdweb::traits::*;
-use stdweb::web::{document, Element, INode, window};
 
-use crate::canvas::Canvas;
-use crate::scoreboard::Scoreboard;
+use crate::history::{History, Snapshot};
+use crate::outcome::{Merge, MoveOutcome, Slide, Spawn};
+use crate::rng::{Rng, SplitMix64};
+use crate::save::{self, LoadError, Position, SavedGame, SavedState};
+
+/// Chance in percent that a spawned tile is a 4 instead of a 2
+pub const FOUR_CHANCE: usize = 10;
+/// Number of moves that can be undone by default
+const HISTORY_CAPACITY: usize = 100;
+/// Fewest and most cells across or down the boards the frontends offer
+pub const MIN_SIZE: usize = 2;
+pub const MAX_SIZE: usize = 8;
+
+/// Parse a board shape like "5x4" (width by height) or "5" for 5x5, `None`
+/// unless both sides are between `MIN_SIZE` and `MAX_SIZE`
+pub fn parse_shape(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = match text.split_once('x') {
+        Some((width, height)) => (width.parse().ok()?, height.parse().ok()?),
+        None => {
+            let size = text.parse().ok()?;
+            (size, size)
+        }
+    };
+    match (width, height) {
+        (MIN_SIZE..=MAX_SIZE, MIN_SIZE..=MAX_SIZE) => Some((width, height)),
+        _ => None,
+    }
+}
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Down,
@@ -13,12 +37,31 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+}
+
+#[derive(Clone, Debug)]
 pub struct Game {
     board: Vec<usize>,
-    size: usize,
+    width: usize,
+    height: usize,
     score: usize,
     best: usize,
+    moves: usize,
     finished: bool,
+    target: usize,
+    won: bool,
+    keep_playing: bool,
+    seed: u64,
+    rng: Box<dyn Rng>,
+    spawned: Option<(usize, usize)>,
+    history: History,
 }
 
 impl Game {
@@ -26,124 +69,138 @@ impl Game {
         Game::from_size(4)
     }
 
+    /// Create an empty square game whose tiles are spawned from seed 0
     pub fn from_size(size: usize) -> Self {
+        Game::from_seed(size, 0)
+    }
+
+    /// Create an empty square game whose tiles are spawned from `seed`
+    pub fn from_seed(size: usize, seed: u64) -> Self {
+        Game::from_shape(size, size, seed)
+    }
+
+    /// Create an empty game `width` cells across and `height` cells down
+    /// whose tiles are spawned from `seed`
+    ///
+    /// Two games created from the same shape and seed receiving the same
+    /// moves will play out exactly the same.
+    pub fn from_shape(width: usize, height: usize, seed: u64) -> Self {
         Game {
-            board: vec![0; size * size],
-            size,
+            board: vec![0; width * height],
+            width,
+            height,
             score: 0,
             best: 0,
+            moves: 0,
             finished: false,
+            target: 2048,
+            won: false,
+            keep_playing: false,
+            seed,
+            rng: Box::new(SplitMix64::from_seed(seed)),
+            spawned: None,
+            history: History::with_capacity(HISTORY_CAPACITY),
         }
     }
 
-    /// This is a temporary implementation to generate a new cell and
-    /// will be replaced once I've figured out how to generate random
-    /// numbers in WASM.
-    pub fn seed_cell(&mut self, seed: usize) {
+    /// Spawn a 2 (or, rarely, a 4) in a random empty cell
+    ///
+    /// The game is marked as finished once no move is left after seeding.
+    pub fn seed_cell(&mut self) -> Option<Spawn> {
         let mut candidates: Vec<usize> = Vec::new();
-        for i in 0..self.board.len() {
-            if self.board[i] == 0 {
+        for (i, &value) in self.board.iter().enumerate() {
+            if value == 0 {
                 candidates.push(i);
             }
         }
-        match seed % 9 {
-            0 => {
-                self.board[candidates[seed % candidates.len()]] = 4;
-            }
-            _ => {
-                self.board[candidates[seed % candidates.len()]] = 2;
-            }
+        if candidates.is_empty() {
+            self.finished = !self.has_moves();
+            return None;
         }
+        let cell = candidates[self.rng.below(candidates.len())];
+        let value = if self.rng.below(100) < FOUR_CHANCE {
+            4
+        } else {
+            2
+        };
+        let spawn = Spawn {
+            position: (cell % self.width, cell / self.width),
+            value,
+        };
+        self.place_tile(spawn);
+        Some(spawn)
+    }
+
+    /// Put a specific tile on the board as if it had been spawned, e.g. when
+    /// replaying recorded spawns
+    pub fn place_tile(&mut self, spawn: Spawn) {
+        let (x, y) = spawn.position;
+        self.set_state(x, y, spawn.value);
+        self.spawned = Some(spawn.position);
+        self.finished = !self.has_moves();
     }
 
     pub fn is_finished(&self) -> bool {
         self.finished
     }
 
-    pub fn draw_board(&self, canvas: &Canvas) {
-        canvas.clear_all();
-        for y in 0..self.size {
-            for x in 0..self.size {
-                if self.get_state(x, y) != 0 {
-                    canvas.draw_tile(
-                        x,
-                        y,
-                        self.get_size(),
-                        self.get_state(x, y),
-                        self.foreground_color(x, y),
-                        self.background_color(x, y),
-                    );
-                }
-            }
-        }
+    /// Whether a tile of at least the target value has been created
+    pub fn is_won(&self) -> bool {
+        self.won
     }
 
-    pub fn draw_score(&self, scoreboard: &Scoreboard) {
-        scoreboard
-            .scoreboard
-            .set_text_content(&format!("{}", self.score));
-        scoreboard.best.set_text_content(&format!("{}", self.best));
-    }
-
-    fn foreground_color(&self, x: usize, y: usize) -> &str {
-        match self.get_state(x, y) {
-            0 => &"#898077",
-            2 => &"#898077",
-            4 => &"#898077",
-            8 => &"#f9f6f2",
-            16 => &"#f9f6f2",
-            32 => &"#f9f6f2",
-            64 => &"#f9f6f2",
-            128 => &"#f9f6f2",
-            256 => &"#f9f6f2",
-            512 => &"#f9f6f2",
-            1024 => &"#f9f6f2",
-            2048 => &"#f9f6f2",
-            4096 => &"#f9f6f2",
-            8192 => &"#f9f6f2",
-            16384 => &"#f9f6f2",
-            32768 => &"#f9f6f2",
-            65536 => &"#f9f6f2",
-            131072 => &"#f9f6f2",
-            _ => &"#f9f6f2",
-        }
+    /// Whether the player chose to continue after reaching the target tile
+    pub fn is_keep_playing(&self) -> bool {
+        self.keep_playing
+    }
+
+    /// Continue playing past the target tile without winning again
+    pub fn keep_playing(&mut self) {
+        self.keep_playing = true;
+    }
+
+    /// Whether the game has a result worth keeping in the high scores and
+    /// statistics: it's over or the target tile was reached, unlike a game
+    /// abandoned halfway
+    pub fn has_result(&self) -> bool {
+        self.finished || self.won
     }
 
-    fn background_color(&self, x: usize, y: usize) -> &str {
-        match self.get_state(x, y) {
-            0 => &"#f9f6f2",
-            2 => &"#eee4da",
-            4 => &"#ede0c8",
-            8 => &"#f2b179",
-            16 => &"#f59563",
-            32 => &"#f67c5f",
-            64 => &"#f65e3b",
-            128 => &"#edcf72",
-            256 => &"#edcc61",
-            512 => &"#edc850",
-            1024 => &"#edc53f",
-            2048 => &"#edc22e",
-            4096 => &"#fb736d",
-            8192 => &"#fb5c5a",
-            16384 => &"#f0513b",
-            32768 => &"#6baed5",
-            65536 => &"#569be0",
-            131072 => &"#1883cc",
-            _ => &"#3c3a32",
+    /// Moves are accepted unless the game is over or the victory screen is up
+    pub fn accepts_moves(&self) -> bool {
+        !self.finished && (!self.won || self.keep_playing)
+    }
+
+    /// Check whether there is an empty cell or a pair of equal neighbours
+    pub fn has_moves(&self) -> bool {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.get_state(x, y);
+                if value == 0 {
+                    return true;
+                }
+                if x + 1 < self.width && self.get_state(x + 1, y) == value {
+                    return true;
+                }
+                if y + 1 < self.height && self.get_state(x, y + 1) == value {
+                    return true;
+                }
+            }
         }
+        false
     }
 
     pub fn set_state(&mut self, x: usize, y: usize, value: usize) {
-        if x < self.size && y < self.size {
-            self.board[y * self.size + x] = value;
+        if x < self.width && y < self.height {
+            self.board[y * self.width + x] = value;
         } else {
             panic!("({},{}) is out ouf bounds!", x, y);
         }
     }
 
     pub fn get_state(&self, x: usize, y: usize) -> usize {
-        if x < self.size && y < self.size {
-            return self.board[y * self.size + x];
+        if x < self.width && y < self.height {
+            return self.board[y * self.width + x];
         }
         panic!("({},{}) is out ouf bounds!", x, y);
     }
@@ -152,8 +209,33 @@ impl Game {
         self.set_state(x, y, 2 * self.get_state(x, y));
     }
 
-    pub fn set_states(&mut self, states: Vec<usize>) {
+    /// Replace the board, which must have `width * height` cells holding 0
+    /// or powers of two
+    pub fn set_states(&mut self, states: Vec<usize>) -> Result<(), LoadError> {
+        save::validate_board(self.width, self.height, &states)?;
         self.board = states;
+        Ok(())
+    }
+
+    /// Restart the random number generator from `seed`
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Box::new(SplitMix64::from_seed(seed));
+    }
+
+    /// Whether the generator's state can be saved, which makes spawns
+    /// reproducible from a saved position
+    pub fn can_save_rng(&self) -> bool {
+        self.rng.get_state().is_some()
+    }
+
+    /// Replace the random number generator used for spawning tiles
+    pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+        self.rng = rng;
+    }
+
+    pub fn set_target_tile(&mut self, target: usize) {
+        self.target = target;
     }
 
     pub fn set_best(&mut self, best: usize) {
@@ -172,15 +254,70 @@ impl Game {
         self.best
     }
 
-    pub fn get_size(&self) -> usize {
-        self.size
+    /// Number of moves made in this game, undone moves don't count
+    pub fn get_moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Value of the largest tile on the board
+    pub fn get_max_tile(&self) -> usize {
+        self.board.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Number of cells across
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells down
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_target_tile(&self) -> usize {
+        self.target
+    }
+
+    /// Position of the most recently spawned tile
+    pub fn get_last_spawn(&self) -> Option<(usize, usize)> {
+        self.spawned
+    }
+
+    /// The seed the random number generator was last started from
+    pub fn get_seed(&self) -> u64 {
+        self.seed
     }
 
     pub fn step(&mut self, direction: &Direction) -> bool {
-        let mut progress: bool = false;
+        self.shift(direction).moved()
+    }
+
+    /// Make a move and spawn a new tile, reporting which tiles slid and
+    /// merged, the points scored and the spawned tile
+    ///
+    /// Nothing is spawned if the move was illegal.
+    pub fn play(&mut self, direction: &Direction) -> MoveOutcome {
+        let mut outcome = self.shift(direction);
+        if outcome.moved() {
+            outcome.spawn = self.seed_cell();
+        }
+        outcome
+    }
+
+    /// Slide and merge all tiles in `direction` without spawning a new one
+    pub fn shift(&mut self, direction: &Direction) -> MoveOutcome {
+        // Games without a history, like random playouts, skip the copy
+        let snapshot = match self.history.get_capacity() {
+            0 => None,
+            _ => Some(self.snapshot()),
+        };
+        let mut outcome = MoveOutcome::default();
         let (x_transversal, y_transversal) = self.build_transveral(direction);
-        let mut merged: std::collections::HashSet<(usize, usize)> =
-            std::collections::HashSet::new();
+        let mut merged: HashSet<(usize, usize)> = HashSet::new();
+        // cell each tile currently on the board started the move in
+        let mut origins: Vec<(usize, usize)> = (0..self.board.len())
+            .map(|i| (i % self.width, i / self.width))
+            .collect();
         for y in y_transversal {
             for x in x_transversal.clone() {
                 let value_c = self.get_state(x, y);
@@ -188,42 +325,241 @@ impl Game {
                 let value_t = self.get_state(x_t, y_t);
 
                 if value_c != 0 && (x != x_t || y != y_t) {
-                    progress = true;
+                    let origin_c = origins[y * self.width + x];
+                    let origin_t = origins[y_t * self.width + x_t];
+                    origins[y_t * self.width + x_t] = origin_c;
                     if value_c != value_t {
                         self.set_state(x_t, y_t, value_c);
                         self.set_state(x, y, 0);
+                        outcome.slides.push(Slide {
+                            from: origin_c,
+                            to: (x_t, y_t),
+                        });
                     } else {
                         merged.insert((x_t, y_t));
                         self.double_state(x_t, y_t);
                         self.set_state(x, y, 0);
-                        self.score += self.get_state(x_t, y_t);
+                        let value = self.get_state(x_t, y_t);
+                        self.score += value;
+                        outcome.score_delta += value;
+                        if value >= self.target {
+                            self.won = true;
+                        }
+                        // the tile merged into is reported as a source instead
+                        outcome.slides.retain(|slide| slide.to != (x_t, y_t));
+                        outcome.merges.push(Merge {
+                            sources: [origin_t, origin_c],
+                            target: (x_t, y_t),
+                            value,
+                        });
                     }
                 }
             }
         }
-        progress
+        if outcome.moved() {
+            self.moves += 1;
+            match snapshot {
+                Some(snapshot) => self.history.push(snapshot),
+                None => self.history.clear_redo(),
+            }
+        }
+        outcome
     }
 
-    /// Reset self.state, self.score and self.finished
+    /// Take back the last move, returns whether there was one to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.moves = self.moves.saturating_sub(1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Play the last undone move again, returns whether there was one
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.moves += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Limit the number of undos per game for a "limited undo" mode,
+    /// `None` allows unlimited undos
+    pub fn set_undo_budget(&mut self, budget: Option<usize>) {
+        self.history.set_budget(budget);
+    }
+
+    /// Number of undos left in this game, `None` means unlimited
+    pub fn get_undos_left(&self) -> Option<usize> {
+        self.history.undos_left()
+    }
+
+    /// Number of undos taken in this game
+    pub fn get_undos_used(&self) -> usize {
+        self.history.undos_used()
+    }
+
+    /// Set how many moves can be undone, 0 disables the history
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
+    }
+
+    /// Reset self.state, self.score, self.finished and the win state
     pub fn clear(&mut self) {
         self.board = vec![0; self.board.len()];
         self.best = std::cmp::max(self.best, self.score);
         self.score = 0;
+        self.moves = 0;
         self.finished = false;
+        self.won = false;
+        self.keep_playing = false;
+        self.spawned = None;
+        self.history.clear();
     }
 
-    /// Store best score in local storage
-    pub fn save_best(&self) {
-        match window().local_storage().insert(&"best", &format!("{}", self.get_best())) {
-            Ok(_) => {}
-            Err(_) => {
-                console!(log, "Failed to save high score to local storage!");
-            }
+    /// Capture the game without its history, e.g. for sharing it
+    pub fn position(&self) -> Position {
+        Position {
+            version: save::VERSION,
+            width: self.width,
+            height: self.height,
+            board: self.board.clone(),
+            score: self.score,
+            best: self.best,
+            moves: self.moves,
+            target: self.target,
+            finished: self.finished,
+            won: self.won,
+            keep_playing: self.keep_playing,
+            seed: self.seed,
+            rng_state: self.rng.get_state(),
+        }
+    }
+
+    /// Set up a game from a position, checking that its board fits its shape
+    pub fn from_position(position: Position) -> Result<Self, LoadError> {
+        save::validate_board(position.width, position.height, &position.board)?;
+        Ok(Game {
+            board: position.board,
+            width: position.width,
+            height: position.height,
+            score: position.score,
+            best: position.best,
+            moves: position.moves,
+            finished: position.finished,
+            target: position.target,
+            won: position.won,
+            keep_playing: position.keep_playing,
+            seed: position.seed,
+            rng: Box::new(SplitMix64::from_seed(
+                position.rng_state.unwrap_or(position.seed),
+            )),
+            spawned: None,
+            history: History::with_capacity(HISTORY_CAPACITY),
+        })
+    }
+
+    /// Capture the whole game, including its history, for resuming later
+    pub fn save(&self) -> SavedGame {
+        let save_state = |snapshot: &Snapshot| SavedState {
+            board: snapshot.board.clone(),
+            score: snapshot.score,
+            finished: snapshot.finished,
+            won: snapshot.won,
+            spawned: snapshot.spawned,
+            rng_state: snapshot.rng.get_state(),
+        };
+        SavedGame {
+            position: self.position(),
+            spawned: self.spawned,
+            history_capacity: self.history.get_capacity(),
+            undo_budget: self.history.get_budget(),
+            undos_used: self.history.undos_used(),
+            undo: self.history.undo_states().map(save_state).collect(),
+            redo: self.history.redo_states().map(save_state).collect(),
         }
     }
 
+    /// Resume a saved game, checking that all boards fit its shape
+    pub fn load(saved: SavedGame) -> Result<Self, LoadError> {
+        let mut game = Game::from_position(saved.position)?;
+        let (width, height) = (game.width, game.height);
+        let seed = game.seed;
+        let load_state = |state: SavedState| -> Result<Snapshot, LoadError> {
+            save::validate_board(width, height, &state.board)?;
+            save::validate_position(width, height, state.spawned)?;
+            Ok(Snapshot {
+                board: state.board,
+                score: state.score,
+                finished: state.finished,
+                won: state.won,
+                spawned: state.spawned,
+                rng: Box::new(SplitMix64::from_seed(state.rng_state.unwrap_or(seed))),
+            })
+        };
+
+        save::validate_position(width, height, saved.spawned)?;
+        let undo = saved
+            .undo
+            .into_iter()
+            .map(load_state)
+            .collect::<Result<_, _>>()?;
+        let redo = saved
+            .redo
+            .into_iter()
+            .map(load_state)
+            .collect::<Result<_, _>>()?;
+
+        game.spawned = saved.spawned;
+        game.history = History::from_parts(
+            saved.history_capacity,
+            undo,
+            redo,
+            saved.undo_budget,
+            saved.undos_used,
+        );
+        Ok(game)
+    }
+
     // private helper functions
 
+    /// Capture the state a move can change
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            score: self.score,
+            finished: self.finished,
+            won: self.won,
+            spawned: self.spawned,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Return to a previously captured state
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.score = snapshot.score;
+        self.finished = snapshot.finished;
+        self.won = snapshot.won;
+        self.spawned = snapshot.spawned;
+        self.rng = snapshot.rng;
+    }
+
     /// Return position of cell to be merged with or moved to (x,y)
     fn get_target(
         &self,
@@ -249,17 +585,13 @@ impl Game {
 
     /// Check whether (x,y) is a position in self.board.state
     fn legal_position(&self, x: i32, y: i32) -> bool {
-        0 <= x && x < self.size as i32 && 0 <= y && y < self.size as i32
+        0 <= x && x < self.width as i32 && 0 <= y && y < self.height as i32
     }
 
     /// Build a list of positions to traverse in the right order
     fn build_transveral(&self, direction: &Direction) -> (Vec<usize>, Vec<usize>) {
-        let mut x_transversal: Vec<usize> = Vec::new();
-        let mut y_transversal: Vec<usize> = Vec::new();
-        for i in 0..self.size {
-            x_transversal.push(i);
-            y_transversal.push(i);
-        }
+        let mut x_transversal: Vec<usize> = (0..self.width).collect();
+        let mut y_transversal: Vec<usize> = (0..self.height).collect();
         match direction {
             Direction::Right => x_transversal.reverse(),
             Direction::Down => y_transversal.reverse(),
@@ -279,6 +611,12 @@ impl Game {
     }
 }
 
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
 impl std::fmt::Display for Game {
     /// Creates a text representation of self.state as in
     /// +-----------+-----------+-----------+-----------+
@@ -307,8 +645,8 @@ impl std::fmt::Display for Game {
     /// |           |           |           |           |
     /// +-----------+-----------+-----------+-----------+
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let print_width: usize = 1 + self.size * 12;
-        let print_height: usize = 1 + self.size * 6;
+        let print_width: usize = 1 + self.width * 12;
+        let print_height: usize = 1 + self.height * 6;
         let mut temp: Vec<char> = Vec::new();
 
         // create empty board with cell borders
@@ -325,8 +663,8 @@ impl std::fmt::Display for Game {
         }
 
         // fill in cells with proper offset
-        for y in 0..self.size {
-            for x in 0..self.size {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 if self.get_state(x, y) > 0 {
                     // get cell value
                     let cell_state: Vec<char> = self
@@ -347,3 +685,158 @@ impl std::fmt::Display for Game {
         write!(f, "{}", temp.iter().collect::<String>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game of `width` by `height` cells holding `states`, row by row
+    fn board(width: usize, height: usize, states: &[usize]) -> Game {
+        let mut game = Game::from_shape(width, height, 0);
+        game.set_states(states.to_vec()).unwrap();
+        game
+    }
+
+    #[test]
+    fn ends_once_a_full_board_has_no_merges() {
+        let mut game = board(2, 2, &[2, 4, 0, 2]);
+        game.place_tile(Spawn {
+            position: (0, 1),
+            value: 8,
+        });
+        assert!(!game.has_moves());
+        assert!(game.is_finished());
+        assert!(!game.accepts_moves());
+
+        let mut game = board(2, 2, &[2, 4, 0, 4]);
+        game.place_tile(Spawn {
+            position: (0, 1),
+            value: 8,
+        });
+        assert!(game.has_moves());
+        assert!(!game.is_finished());
+    }
+
+    #[test]
+    fn finished_games_refuse_moves() {
+        let mut game = board(2, 2, &[2, 4, 8, 2]);
+        assert_eq!(game.seed_cell(), None);
+        assert!(game.is_finished());
+        for direction in Direction::ALL.iter() {
+            assert!(!game.play(direction).moved());
+        }
+        assert_eq!(game.get_states(), vec![2, 4, 8, 2]);
+        assert_eq!((game.get_score(), game.get_moves()), (0, 0));
+    }
+
+    #[test]
+    fn reaching_the_target_wins_once() {
+        let mut game = board(4, 1, &[2, 2, 0, 0]);
+        game.set_target_tile(8);
+        game.shift(&Direction::Left);
+        assert!(!game.is_won());
+        assert!(game.accepts_moves());
+
+        game.set_states(vec![4, 4, 2, 0]).unwrap();
+        game.shift(&Direction::Left);
+        assert!(game.is_won());
+        assert!(!game.accepts_moves());
+
+        game.keep_playing();
+        assert!(game.accepts_moves());
+        // Another tile of the target value doesn't stop the game again
+        game.set_states(vec![8, 4, 4, 0]).unwrap();
+        game.shift(&Direction::Left);
+        assert_eq!(game.get_states(), vec![8, 8, 0, 0]);
+        assert!(game.is_won() && game.is_keep_playing());
+        assert!(game.accepts_moves());
+
+        game.clear();
+        assert!(!game.is_won() && !game.is_keep_playing());
+    }
+
+    #[test]
+    fn reports_every_merge_of_a_row() {
+        let mut game = board(4, 1, &[2, 2, 2, 2]);
+        let outcome = game.shift(&Direction::Left);
+        assert_eq!(game.get_states(), vec![4, 4, 0, 0]);
+        assert_eq!(outcome.slides, vec![]);
+        assert_eq!(
+            outcome.merges,
+            vec![
+                Merge {
+                    sources: [(0, 0), (1, 0)],
+                    target: (0, 0),
+                    value: 4,
+                },
+                Merge {
+                    sources: [(2, 0), (3, 0)],
+                    target: (1, 0),
+                    value: 4,
+                },
+            ]
+        );
+        assert_eq!(outcome.score_delta, 8);
+        assert_eq!(game.get_score(), 8);
+        assert_eq!(outcome.spawn, None);
+    }
+
+    #[test]
+    fn reports_slides_from_where_tiles_started() {
+        let mut game = board(2, 4, &[0, 2, 4, 0, 0, 0, 4, 8]);
+        let outcome = game.shift(&Direction::Down);
+        assert_eq!(game.get_states(), vec![0, 0, 0, 0, 0, 2, 8, 8]);
+        assert_eq!(
+            outcome.slides,
+            vec![Slide {
+                from: (1, 0),
+                to: (1, 2),
+            }]
+        );
+        assert_eq!(
+            outcome.merges,
+            vec![Merge {
+                sources: [(0, 3), (0, 1)],
+                target: (0, 3),
+                value: 8,
+            }]
+        );
+        assert_eq!(outcome.score_delta, 8);
+    }
+
+    #[test]
+    fn reports_the_spawned_tile() {
+        let mut game = board(4, 1, &[0, 2, 0, 2]);
+        let outcome = game.play(&Direction::Right);
+        let spawn = outcome.spawn.unwrap();
+        let (x, y) = spawn.position;
+        assert!(x < 3 && y == 0);
+        assert!(spawn.value == 2 || spawn.value == 4);
+        assert_eq!(game.get_state(x, y), spawn.value);
+        assert_eq!(game.get_state(3, 0), 4);
+        assert_eq!(game.get_last_spawn(), Some(spawn.position));
+    }
+
+    #[test]
+    fn plays_without_a_history() {
+        let mut game = board(4, 1, &[2, 2, 0, 4]);
+        game.shift(&Direction::Left);
+        game.undo();
+        game.set_history_capacity(0);
+        assert!(game.can_redo());
+        game.shift(&Direction::Right);
+        assert_eq!(game.get_states(), vec![0, 0, 4, 4]);
+        assert!(!game.can_undo() && !game.can_redo());
+    }
+
+    #[test]
+    fn blocked_moves_change_nothing() {
+        let mut game = board(4, 1, &[2, 4, 0, 0]);
+        let outcome = game.play(&Direction::Left);
+        assert_eq!(outcome, MoveOutcome::default());
+        assert!(!outcome.moved());
+        assert_eq!(game.get_states(), vec![2, 4, 0, 0]);
+        assert_eq!((game.get_score(), game.get_moves()), (0, 0));
+        assert!(!game.can_undo());
+    }
+}
@@ -0,0 +1,246 @@
+//! A bounded undo/redo history of game states.
+
+use std::collections::VecDeque;
+
+use crate::rng::Rng;
+
+/// Everything needed to restore a game to the state before a move
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub board: Vec<usize>,
+    pub score: usize,
+    pub finished: bool,
+    pub won: bool,
+    /// Position of the tile spawned right before this state was recorded
+    pub spawned: Option<(usize, usize)>,
+    /// The generator is restored too, so undoing a move and playing it
+    /// again spawns the same tile.
+    pub rng: Box<dyn Rng>,
+}
+
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    capacity: usize,
+    budget: Option<usize>,
+    used: usize,
+}
+
+impl History {
+    pub fn with_capacity(capacity: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity,
+            budget: None,
+            used: 0,
+        }
+    }
+
+    /// Rebuild a history from saved states, oldest first
+    pub fn from_parts(
+        capacity: usize,
+        undo: Vec<Snapshot>,
+        redo: Vec<Snapshot>,
+        budget: Option<usize>,
+        used: usize,
+    ) -> Self {
+        let mut history = History {
+            undo: undo.into(),
+            redo,
+            capacity,
+            budget,
+            used,
+        };
+        history.set_capacity(capacity);
+        history
+    }
+
+    /// States that can be undone, oldest first
+    pub fn undo_states(&self) -> impl Iterator<Item = &Snapshot> {
+        self.undo.iter()
+    }
+
+    /// States that can be redone, the next one to redo last
+    pub fn redo_states(&self) -> impl Iterator<Item = &Snapshot> {
+        self.redo.iter()
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Record the state before a new move, dropping the oldest entry when
+    /// full. A new move invalidates everything that could be redone.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        self.clear_redo();
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+    }
+
+    /// Forget the states that could be redone, e.g. after a new move the
+    /// history has no room for
+    pub fn clear_redo(&mut self) {
+        self.redo.clear();
+    }
+
+    /// Take the most recent state, recording `current` so it can be redone
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        if !self.can_undo() {
+            return None;
+        }
+        let snapshot = self.undo.pop_back()?;
+        self.redo.push(current);
+        self.used += 1;
+        Some(snapshot)
+    }
+
+    /// Take the most recently undone state, recording `current` so it can
+    /// be undone again unless the history keeps nothing
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        if self.capacity > 0 {
+            if self.undo.len() == self.capacity {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(current);
+        }
+        Some(snapshot)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() && self.undos_left() != Some(0)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Limit the number of undos per game, `None` means unlimited
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
+    /// Number of undos left in this game, `None` means unlimited
+    pub fn undos_left(&self) -> Option<usize> {
+        self.budget.map(|budget| budget.saturating_sub(self.used))
+    }
+
+    /// Number of undos taken in this game
+    pub fn undos_used(&self) -> usize {
+        self.used
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.undo.len() > capacity {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Forget all states and reset the undo budget for a new game
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    /// A state told apart from others by its score
+    fn state(score: usize) -> Snapshot {
+        Snapshot {
+            board: vec![0; 4],
+            score,
+            finished: false,
+            won: false,
+            spawned: None,
+            rng: Box::new(SplitMix64::from_seed(0)),
+        }
+    }
+
+    fn scores<'a>(states: impl Iterator<Item = &'a Snapshot>) -> Vec<usize> {
+        states.map(|snapshot| snapshot.score).collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_state_when_full() {
+        let mut history = History::with_capacity(3);
+        for score in 0..5 {
+            history.push(state(score));
+        }
+        assert_eq!(scores(history.undo_states()), vec![2, 3, 4]);
+        history.set_capacity(2);
+        assert_eq!(scores(history.undo_states()), vec![3, 4]);
+
+        assert_eq!(history.undo(state(5)).map(|s| s.score), Some(4));
+        assert_eq!(history.undo(state(4)).map(|s| s.score), Some(3));
+        assert!(history.undo(state(3)).is_none());
+        assert_eq!(scores(history.redo_states()), vec![5, 4]);
+    }
+
+    #[test]
+    fn stops_undoing_once_the_budget_is_spent() {
+        let mut history = History::with_capacity(10);
+        history.set_budget(Some(2));
+        for score in 0..5 {
+            history.push(state(score));
+        }
+        assert!(history.undo(state(5)).is_some());
+        // Redoing doesn't give the undo back
+        assert!(history.redo(state(4)).is_some());
+        assert!(history.undo(state(5)).is_some());
+        assert_eq!(history.undos_left(), Some(0));
+        assert!(!history.can_undo());
+        assert!(history.undo(state(4)).is_none());
+        assert_eq!(history.undos_used(), 2);
+
+        history.clear();
+        history.push(state(0));
+        assert_eq!(history.undos_left(), Some(2));
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn a_new_move_clears_the_redos() {
+        let mut history = History::with_capacity(10);
+        history.push(state(0));
+        history.push(state(1));
+        history.undo(state(2));
+        assert!(history.can_redo());
+        history.push(state(1));
+        assert!(!history.can_redo());
+        assert!(history.redo(state(3)).is_none());
+        assert_eq!(scores(history.undo_states()), vec![0, 1]);
+    }
+
+    #[test]
+    fn keeps_nothing_without_capacity() {
+        let mut history = History::with_capacity(0);
+        history.push(state(0));
+        assert!(!history.can_undo());
+        assert!(history.undo(state(1)).is_none());
+
+        let mut history = History::with_capacity(2);
+        history.push(state(0));
+        history.undo(state(1));
+        history.set_capacity(0);
+        assert_eq!(history.redo(state(0)).map(|s| s.score), Some(1));
+        assert_eq!(history.undo_states().count(), 0);
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,21 @@
+//! The game engine behind rs2048.
+//!
+//! Everything in here is plain Rust without any web dependencies so the
+//! rules can be used from native binaries, tests and bots alike. The
+//! browser frontend lives in the `rs2048` binary behind the `web` feature.
+
+pub mod ai;
+pub mod bitboard;
+mod encoding;
+pub mod game;
+pub mod history;
+pub mod outcome;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod scores;
+pub mod stats;
+pub mod theme;
+
+pub use crate::game::{Direction, Game};
+pub use crate::outcome::MoveOutcome;
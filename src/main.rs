@@ -1,99 +1,640 @@
 #[macro_use]
 extern crate stdweb;
 use stdweb::traits::*;
-use stdweb::web::{document, event, window, confirm};
+use stdweb::unstable::TryInto;
+use stdweb::web::html_element::SelectElement;
+use stdweb::web::{confirm, document, event, window};
 
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 
-mod canvas;
-mod game;
-mod scoreboard;
-mod util;
+mod web;
 
-use crate::canvas::Canvas;
-use crate::game::Direction;
-use crate::game::Game;
-use crate::scoreboard::Scoreboard;
-use crate::util::*;
+use rs2048::ai::strategy::{self, Strategy, STRATEGIES};
+use rs2048::ai::MonteCarlo;
+use rs2048::game::parse_shape;
+use rs2048::replay::Recorder;
+use rs2048::scores::{HighScores, Score, Variant};
+use rs2048::stats::{GameRecord, Stats};
+use rs2048::theme::BUILTIN;
+use rs2048::{Direction, Game};
+
+use crate::web::canvas::{Canvas, OverlayAction, Summary};
+use crate::web::replay::ReplayViewer;
+use crate::web::scoreboard::Scoreboard;
+use crate::web::share;
+use crate::web::storage;
+use crate::web::theme;
+use crate::web::util::*;
 
 // Set this to some positive number of milliseconds
-// to get a game tick at that interval.
-const TICK_MS: Option<u32> = None;
+// to get a game tick at that interval. Autoplay
+// makes one move per tick and replays are played
+// back a move every few ticks.
+const TICK_MS: Option<u32> = Some(100);
+
+// Set this to some number of undos per game to
+// play in "limited undo" mode.
+const UNDO_BUDGET: Option<usize> = None;
+
+// How long a move is animated in milliseconds,
+// 0 disables animations.
+const ANIMATION_MS: f64 = 120.0;
+
+// How many moves to remember while a move is
+// being animated.
+const MAX_QUEUED_MOVES: usize = 4;
+
+// How far in pixels and how fast in pixels per
+// millisecond a swipe on the board has to be to
+// count as a move.
+const MIN_SWIPE_DISTANCE: f64 = 30.0;
+const MIN_SWIPE_VELOCITY: f64 = 0.1;
+
+// How long in milliseconds Monte Carlo may play
+// out random games for a hint or an autoplay move,
+// and how many it plays after each move at most.
+const MONTECARLO_MS: f64 = 200.0;
+const MONTECARLO_ROLLOUTS: usize = 10_000;
+
+/// Redraw the board, the scores and any overlay, a finished game is
+/// reported on with the time on `stopwatch` and whether it set a record
+fn draw(
+    game: &Game,
+    canvas: &mut Canvas,
+    scoreboard: &Scoreboard,
+    stopwatch: &Stopwatch,
+    new_best: bool,
+) {
+    canvas.set_shape(game.get_width(), game.get_height());
+    scoreboard.draw_score(game);
+    canvas.draw_board(game);
+    if game.is_won() && !game.is_keep_playing() {
+        canvas.draw_victory();
+    } else if game.is_finished() {
+        canvas.draw_game_over(&Summary {
+            score: game.get_score(),
+            max_tile: game.get_max_tile(),
+            moves: game.get_moves(),
+            duration: stopwatch.elapsed(now()),
+            new_best,
+            can_undo: game.can_undo(),
+        });
+    }
+}
+
+/// Redraw the replay's board and where playback is
+fn draw_replay(viewer: &ReplayViewer, canvas: &mut Canvas, scoreboard: &Scoreboard) {
+    let game = viewer.get_game();
+    canvas.set_shape(game.get_width(), game.get_height());
+    scoreboard.draw_score(game);
+    canvas.draw_board(viewer.get_game());
+    canvas.draw_caption(&viewer.status());
+}
+
+/// The board shape picker
+fn size_picker() -> SelectElement {
+    document()
+        .get_element_by_id("size")
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn strategy_picker() -> SelectElement {
+    document()
+        .get_element_by_id("strategy")
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// A Monte Carlo player that keeps within `MONTECARLO_MS` a move
+fn montecarlo() -> MonteCarlo {
+    let mut montecarlo = MonteCarlo::new(MONTECARLO_ROLLOUTS, get_seed());
+    montecarlo.set_budget(Some(MONTECARLO_MS));
+    montecarlo.set_threads(1);
+    montecarlo.set_clock(clock);
+    montecarlo
+}
+
+/// The strategy called `name`, set up for the browser
+fn named_strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "montecarlo" => Some(Box::new(montecarlo())),
+        name => strategy::named(name, get_seed()),
+    }
+}
+
+/// List every strategy autoplay can use, with the first one picked
+fn fill_strategy_picker() {
+    let picker = strategy_picker();
+    for name in STRATEGIES.iter() {
+        let option = document().create_element("option").unwrap();
+        let _ = option.set_attribute("value", name);
+        option.set_text_content(&format!("Autoplay: {}", name));
+        picker.append_child(&option);
+    }
+    let _ = picker.set_value(Some(STRATEGIES[0]));
+}
+
+/// Show `game`'s shape in the board shape picker
+fn show_shape(game: &Game) {
+    let shape = format!("{}x{}", game.get_width(), game.get_height());
+    // Shapes that can't be picked, e.g. from a shared position, aren't listed
+    let _ = size_picker().set_value(Some(&shape));
+}
+
+/// The record of `game`'s variant
+fn record(game: &Game, scores: &HighScores) -> usize {
+    // Best scores stored before the table existed still count
+    let old_best = storage::load_best(game.get_width(), game.get_height()).unwrap_or(0);
+    scores.best(&Variant::of(game)).max(old_best)
+}
+
+/// Enter the result of `game`, played for `duration` seconds, in the
+/// high score table and the statistics and show them, returns what was
+/// entered
+fn submit(
+    game: &Game,
+    duration: u64,
+    scores: &mut HighScores,
+    stats: &mut Stats,
+    scoreboard: &Scoreboard,
+) -> (Score, GameRecord) {
+    let variant = Variant::of(game);
+    let date = now();
+    let score = Score::of(game, date);
+    scores.submit(variant, score.clone());
+    storage::save_scores(scores);
+    scoreboard.draw_records(&variant, scores.top(&variant));
+    let record =
+        GameRecord::of(game, duration, date).expect("only games with a result are submitted");
+    stats.record(record.clone());
+    storage::save_stats(stats);
+    scoreboard.draw_stats(&stats.summary(None));
+    (score, record)
+}
+
+/// Take back a result entered by `submit`, once its game is undone
+fn withdraw(
+    submitted: &(Score, GameRecord),
+    scores: &mut HighScores,
+    stats: &mut Stats,
+    scoreboard: &Scoreboard,
+) {
+    let (score, record) = submitted;
+    scores.withdraw(&record.variant, score);
+    storage::save_scores(scores);
+    scoreboard.draw_records(&record.variant, scores.top(&record.variant));
+    stats.withdraw(record);
+    storage::save_stats(stats);
+    scoreboard.draw_stats(&stats.summary(None));
+}
+
+/// What `submit` entered for the finished `game` before the page was
+/// reloaded, `None` if it's not in the statistics
+fn previous_submission(game: &Game, stats: &Stats) -> Option<(Score, GameRecord)> {
+    let variant = Variant::of(game);
+    let record = stats.get_games().iter().rev().find(|record| {
+        (record.variant, record.score, record.moves)
+            == (variant, game.get_score(), game.get_moves())
+    })?;
+    let score = Score {
+        score: record.score,
+        moves: record.moves,
+        max_tile: record.max_tile,
+        date: record.date,
+    };
+    Some((score, record.clone()))
+}
+
+/// Start a game on a board of `width` by `height` cells
+fn new_game(width: usize, height: usize, scores: &HighScores) -> Game {
+    let mut game = Game::from_shape(width, height, get_seed());
+    game.set_best(record(&game, scores));
+    game.set_undo_budget(UNDO_BUDGET);
+    game.seed_cell();
+    game
+}
 
 fn main() {
     // Initialize framework
     stdweb::initialize();
 
     // Game state
-    let mut index: usize = 1;
-    let mut game = Game::new();
-    let canvas = Canvas::new("#canvas");
-    let scoreboard = Scoreboard::new("#scoreboard", "#best");
-    let mut last_mouse_pos = Point::from_data(0, 0);
+    // Start from a position shared by URL, or resume the game
+    // from before the last reload if there is one. A shared position only
+    // replaces a game in progress if the player agrees.
+    let saved = storage::load_game();
+    let shared = share::load_position().filter(|_| {
+        saved
+            .as_ref()
+            .is_none_or(|saved| saved.get_score() == 0 || saved.is_finished())
+            || confirm("Replace your game in progress with the shared position?")
+    });
+    let is_shared = shared.is_some();
+    let resumed = shared.or(saved);
+    let is_new_game = resumed.is_none();
+    let mut scores = storage::load_scores();
+    let mut stats = storage::load_stats();
+    let mut game = resumed.unwrap_or_else(|| new_game(4, 4, &scores));
+    // The game's result once it has been entered in the high score table
+    let mut submitted = match game.is_finished() {
+        true => previous_submission(&game, &stats),
+        false => None,
+    };
+    // Whether that result beat the record, as far as can be told after a
+    // reload
+    let mut new_best =
+        game.is_finished() && game.get_score() > 0 && game.get_score() >= record(&game, &scores);
+    let mut stopwatch = Stopwatch::new(match is_new_game || is_shared {
+        true => 0,
+        false => storage::load_time(),
+    });
+    let mut canvas = Canvas::new("#canvas");
+    let scoreboard = Scoreboard::new("#scoreboard", "#best", "#records", "#stats");
+    let mut swipe = Swipe::new(MIN_SWIPE_DISTANCE, MIN_SWIPE_VELOCITY);
+    let mut queued: VecDeque<Direction> = VecDeque::new();
+    // Hints come from random playouts, which work on any board, autoplay
+    // uses the strategy picked
+    let mut hints = montecarlo();
+    let mut strategy: Box<dyn Strategy> =
+        named_strategy(STRATEGIES[0]).expect("the default strategy exists");
+    let mut autoplay = false;
+    // Open a replay shared by URL on top of the game
+    let mut viewer = share::load_replay().and_then(|replay| ReplayViewer::new(replay).ok());
 
     // Initialize game
+    canvas.set_animation_duration(ANIMATION_MS);
 
-    // Attempt to recover previous best from web storage
-    // XXX Will be silent on fetch / parse failure.
-    if let Some(s) = window().local_storage().get(&"best") {
-        if let Ok(best) = s.parse::<usize>() {
-            game.set_best(best);
-        }
+    // Draw in the theme picked last time
+    let themes = storage::load_themes();
+    let theme_name = storage::load_theme_name().unwrap_or_default();
+    let picked = themes
+        .iter()
+        .find(|theme| theme.name == theme_name)
+        .cloned()
+        .unwrap_or_default();
+    fill_strategy_picker();
+    theme::fill_picker(&themes, &picked.name);
+    theme::style_page(&picked);
+    canvas.set_theme(picked);
+
+    // Show the records of the variant being played
+    game.set_best(record(&game, &scores).max(game.get_best()));
+    let variant = Variant::of(&game);
+    scoreboard.draw_records(&variant, scores.top(&variant));
+    scoreboard.draw_stats(&stats.summary(None));
+
+    if is_new_game || is_shared {
+        game.set_undo_budget(UNDO_BUDGET);
+        storage::save_game(&game);
     }
+    show_shape(&game);
 
-    game.seed_cell(get_seed());
-    game.draw_board(&canvas);
-    game.draw_score(&scoreboard);
+    // Keep recording where the game left off
+    let mut recorder = match is_new_game || is_shared {
+        true => None,
+        false => storage::load_replay(&game),
+    }
+    .unwrap_or_else(|| Recorder::new(&game));
+    storage::save_replay(&recorder);
+
+    match &viewer {
+        Some(viewer) => draw_replay(viewer, &mut canvas, &scoreboard),
+        None => draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best),
+    }
 
     // End initialization of game
 
     // Process a single GameEvent
     let process_event_fn = move |game_event| {
-        let progress = match game_event {
-            GameEvent::MouseDown(event) => {
-                last_mouse_pos.set(event.client_x(), event.client_y());
-                false
+        // While a replay is open the keys control playback
+        if let Some(replay) = viewer.as_mut() {
+            match game_event {
+                GameEvent::KeyDown(event) => {
+                    match event.key().as_ref() {
+                        " " => replay.toggle_playing(),
+                        "ArrowRight" => {
+                            canvas.stop_animation();
+                            if let Some(outcome) = replay.step_forward() {
+                                canvas.start_animation(outcome);
+                            }
+                        }
+                        "ArrowLeft" => {
+                            canvas.stop_animation();
+                            replay.step_back();
+                        }
+                        "Home" => {
+                            canvas.stop_animation();
+                            replay.seek(0);
+                        }
+                        "End" => {
+                            canvas.stop_animation();
+                            replay.seek(replay.len());
+                        }
+                        "+" | "=" => replay.faster(),
+                        "-" => replay.slower(),
+                        "s" => share::copy_replay_url(replay.get_replay()),
+                        "p" | "Escape" => {
+                            viewer = None;
+                            canvas.stop_animation();
+                            draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                            return;
+                        }
+                        _ => {}
+                    }
+                    if !canvas.is_animating() {
+                        draw_replay(replay, &mut canvas, &scoreboard);
+                    }
+                }
+                GameEvent::Frame(timestamp) => {
+                    if canvas.draw_frame(replay.get_game(), timestamp) {
+                        draw_replay(replay, &mut canvas, &scoreboard);
+                    }
+                }
+                GameEvent::Tick => {
+                    if !canvas.is_animating() {
+                        if let Some(outcome) = replay.tick() {
+                            scoreboard.draw_score(replay.get_game());
+                            canvas.start_animation(outcome);
+                        }
+                    }
+                }
+                GameEvent::Exit => {
+                    storage::save_game(&game);
+                    storage::save_replay(&recorder);
+                    storage::save_time(stopwatch.elapsed(now()));
+                }
+                // The picker changes the game, not the replay
+                GameEvent::Shape(..) => show_shape(&game),
+                GameEvent::Resize => {
+                    if canvas.resize() && !canvas.is_animating() {
+                        draw_replay(replay, &mut canvas, &scoreboard);
+                    }
+                }
+                GameEvent::Theme(picked) => {
+                    storage::save_theme_name(&picked.name);
+                    theme::style_page(&picked);
+                    canvas.set_theme(*picked);
+                    if !canvas.is_animating() {
+                        draw_replay(replay, &mut canvas, &scoreboard);
+                    }
+                }
+                GameEvent::Strategy(name) => {
+                    if let Some(picked) = named_strategy(&name) {
+                        strategy = picked;
+                    }
+                }
+                GameEvent::SwipeStart(..)
+                | GameEvent::SwipeEnd(..)
+                | GameEvent::SwipeCancel
+                | GameEvent::Action(..)
+                | GameEvent::Hint => {}
             }
-            GameEvent::MouseUp(event) => {
-                let current_mouse_pos = Point::from_data(event.client_x(), event.client_y());
-                let direction = get_direction(&last_mouse_pos, &current_mouse_pos);
-                game.step(&direction)
+            return;
+        }
+
+        // Taps on the overlays' buttons and the keys doing the same thing
+        let game_event = match game_event {
+            GameEvent::SwipeEnd(point, time) => match canvas.action_at(&point) {
+                Some(action) => {
+                    swipe.cancel();
+                    GameEvent::Action(action)
+                }
+                None => GameEvent::SwipeEnd(point, time),
+            },
+            GameEvent::KeyDown(event) => match event.key().as_ref() {
+                "n" | "Enter" if game.is_finished() => GameEvent::Action(OverlayAction::NewGame),
+                "r" if game.is_finished() || confirm("Reset game?") => {
+                    GameEvent::Action(OverlayAction::NewGame)
+                }
+                key if key == "u" || (key == "z" && event.ctrl_key()) => {
+                    GameEvent::Action(OverlayAction::Undo)
+                }
+                "c" if game.is_won() && !game.is_keep_playing() => {
+                    GameEvent::Action(OverlayAction::KeepGoing)
+                }
+                "h" => GameEvent::Hint,
+                _ => GameEvent::KeyDown(event),
+            },
+            game_event => game_event,
+        };
+
+        let direction = match game_event {
+            GameEvent::SwipeStart(point, time) => {
+                swipe.begin(point, time);
+                None
+            }
+            GameEvent::SwipeEnd(point, time) => swipe.end(point, time),
+            GameEvent::SwipeCancel => {
+                swipe.cancel();
+                None
+            }
+            GameEvent::Action(OverlayAction::NewGame) => {
+                // Games abandoned halfway aren't counted
+                if submitted.take().is_none() && game.has_result() {
+                    submit(
+                        &game,
+                        stopwatch.elapsed(now()),
+                        &mut scores,
+                        &mut stats,
+                        &scoreboard,
+                    );
+                }
+                new_best = false;
+                stopwatch = Stopwatch::default();
+                storage::save_time(0);
+                game.clear();
+                game.reseed(get_seed());
+                game.seed_cell();
+                storage::save_game(&game);
+                recorder = Recorder::new(&game);
+                storage::save_replay(&recorder);
+                queued.clear();
+                canvas.stop_animation();
+                draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                None
+            }
+            GameEvent::Action(OverlayAction::Undo) => {
+                if game.undo() {
+                    recorder.undo();
+                    // The game goes on, its result is entered again once it
+                    // really ends
+                    if let Some(submitted) = submitted.take() {
+                        withdraw(&submitted, &mut scores, &mut stats, &scoreboard);
+                        new_best = false;
+                    }
+                    storage::save_game(&game);
+                    storage::save_replay(&recorder);
+                    queued.clear();
+                    canvas.stop_animation();
+                    draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                }
+                None
+            }
+            GameEvent::Action(OverlayAction::KeepGoing) => {
+                game.keep_playing();
+                storage::save_game(&game);
+                draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                None
             }
             GameEvent::KeyDown(event) => match event.key().as_ref() {
-                "ArrowUp" => game.step(&Direction::Up),
-                "ArrowDown" => game.step(&Direction::Down),
-                "ArrowLeft" => game.step(&Direction::Left),
-                "ArrowRight" => game.step(&Direction::Right),
-                "r" => {
-                    if confirm("Reset game?") {
-                        game.clear();
-                        game.save_best();
-                        true
-                    } else {
-                        false
+                "y" | "Z" if event.ctrl_key() => {
+                    if game.redo() {
+                        recorder.redo();
+                        storage::save_game(&game);
+                        storage::save_replay(&recorder);
+                        queued.clear();
+                        canvas.stop_animation();
+                        draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                    }
+                    None
+                }
+                "a" => {
+                    autoplay = !autoplay;
+                    None
+                }
+                "s" => {
+                    share::copy_share_url(&game);
+                    None
+                }
+                "p" => {
+                    // Watch the current game from the start
+                    if let Ok(replay) = ReplayViewer::new(recorder.get_replay().clone()) {
+                        autoplay = false;
+                        queued.clear();
+                        canvas.stop_animation();
+                        draw_replay(&replay, &mut canvas, &scoreboard);
+                        viewer = Some(replay);
                     }
+                    None
                 }
-                _ => false,
+                "ArrowUp" => Some(Direction::Up),
+                "ArrowDown" => Some(Direction::Down),
+                "ArrowLeft" => Some(Direction::Left),
+                "ArrowRight" => Some(Direction::Right),
+                _ => None,
             },
-            GameEvent::Tick => {
-                // XXX Enable TICK_MS above and
-                // uncomment below to try out the
-                // interval timer.
-
-                // canvas.clear_all();
-                false
+            GameEvent::Frame(timestamp) => {
+                if canvas.draw_frame(&game, timestamp) {
+                    draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                    // Play the next move that came in during the animation
+                    queued.pop_front()
+                } else {
+                    None
+                }
+            }
+            GameEvent::Tick if autoplay => {
+                if !game.accepts_moves() {
+                    autoplay = false;
+                    None
+                } else if canvas.is_animating() {
+                    None
+                } else {
+                    strategy.next_move(&game)
+                }
+            }
+            GameEvent::Tick => None,
+            GameEvent::Hint => {
+                if let Some(direction) = hints.best_move(&game) {
+                    canvas.draw_hint(direction);
+                }
+                None
+            }
+            GameEvent::Strategy(name) => {
+                if let Some(picked) = named_strategy(&name) {
+                    strategy = picked;
+                }
+                None
+            }
+            GameEvent::Shape(width, height) => {
+                if (width, height) != (game.get_width(), game.get_height())
+                    && (game.get_score() == 0 || confirm("Start a new game on this board?"))
+                {
+                    if submitted.take().is_none() && game.has_result() {
+                        submit(
+                            &game,
+                            stopwatch.elapsed(now()),
+                            &mut scores,
+                            &mut stats,
+                            &scoreboard,
+                        );
+                    }
+                    new_best = false;
+                    stopwatch = Stopwatch::default();
+                    storage::save_time(0);
+                    game = new_game(width, height, &scores);
+                    let variant = Variant::of(&game);
+                    scoreboard.draw_records(&variant, scores.top(&variant));
+                    storage::save_game(&game);
+                    recorder = Recorder::new(&game);
+                    storage::save_replay(&recorder);
+                    autoplay = false;
+                    queued.clear();
+                    canvas.stop_animation();
+                    draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                }
+                show_shape(&game);
+                None
+            }
+            GameEvent::Resize => {
+                // Animations redraw the board on every frame anyway
+                if canvas.resize() && !canvas.is_animating() {
+                    draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                }
+                None
+            }
+            GameEvent::Theme(picked) => {
+                storage::save_theme_name(&picked.name);
+                theme::style_page(&picked);
+                canvas.set_theme(*picked);
+                if !canvas.is_animating() {
+                    draw(&game, &mut canvas, &scoreboard, &stopwatch, new_best);
+                }
+                None
             }
             GameEvent::Exit => {
-                game.clear();
-                game.save_best();
-                false
+                // Keep the board, the game is resumed on the next visit
+                storage::save_game(&game);
+                storage::save_replay(&recorder);
+                storage::save_time(stopwatch.elapsed(now()));
+                None
             }
         };
-        if progress {
-            game.seed_cell(get_seed());
-            index += 1;
-            game.draw_score(&scoreboard);
-            game.draw_board(&canvas);
+        if let Some(direction) = direction {
+            if canvas.is_animating() {
+                // Queue moves so fast key presses aren't dropped
+                if queued.len() < MAX_QUEUED_MOVES {
+                    queued.push_back(direction);
+                }
+            } else if game.accepts_moves() {
+                // Ignore moves while the game is over or won
+                let outcome = game.play(&direction);
+                if outcome.moved() {
+                    recorder.record(direction, &outcome);
+                    storage::save_game(&game);
+                    storage::save_replay(&recorder);
+                    stopwatch.start(now());
+                    if game.is_finished() {
+                        stopwatch.stop(now());
+                        storage::save_time(stopwatch.elapsed(now()));
+                        if submitted.is_none() {
+                            new_best = game.get_score() > record(&game, &scores);
+                            submitted = Some(submit(
+                                &game,
+                                stopwatch.elapsed(now()),
+                                &mut scores,
+                                &mut stats,
+                                &scoreboard,
+                            ));
+                        }
+                    }
+                    scoreboard.draw_score(&game);
+                    canvas.start_animation(outcome);
+                }
+            }
         }
     };
 
@@ -102,38 +643,214 @@ fn main() {
     // will work.
     let process_event: Arc<Mutex<dyn FnMut(GameEvent)>> = Arc::new(Mutex::new(process_event_fn));
 
+    // Swipes on the board are reported as pointer events by current
+    // browsers and as mouse or touch events by older ones
+    let board = document().get_element_by_id("canvas").unwrap();
+
     // Add event handler MouseDown
-    document()
-        .get_element_by_id("canvas")
-        .unwrap()
-        .add_event_listener({
-            let process_event = process_event.clone();
-            move |event: event::MouseDownEvent| {
-                event.prevent_default();
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::MouseDownEvent| {
+            event.prevent_default();
+            let point = Point::from_data(f64::from(event.client_x()), f64::from(event.client_y()));
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeStart(
+                point,
+                event.time_stamp().unwrap_or(0.0),
+            ));
+        }
+    });
+
+    // Add event handler MouseUp
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::MouseUpEvent| {
+            event.prevent_default();
+            let point = Point::from_data(f64::from(event.client_x()), f64::from(event.client_y()));
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeEnd(
+                point,
+                event.time_stamp().unwrap_or(0.0),
+            ));
+        }
+    });
+
+    // Add event handler PointerDown
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        let board = board.clone();
+        move |event: event::PointerDownEvent| {
+            if !event.is_primary() {
+                return;
+            }
+            // Keep receiving the pointer when it leaves the board mid-swipe
+            let _ = board.set_pointer_capture(event.pointer_id());
+            let point = Point::from_data(f64::from(event.client_x()), f64::from(event.client_y()));
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeStart(
+                point,
+                event.time_stamp().unwrap_or(0.0),
+            ));
+        }
+    });
+
+    // Add event handler PointerUp
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::PointerUpEvent| {
+            if !event.is_primary() {
+                return;
+            }
+            let point = Point::from_data(f64::from(event.client_x()), f64::from(event.client_y()));
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeEnd(
+                point,
+                event.time_stamp().unwrap_or(0.0),
+            ));
+        }
+    });
+
+    // Add event handler PointerCancel
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |_event: event::PointerCancelEvent| {
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeCancel);
+        }
+    });
+
+    // Add event handler TouchStart
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::TouchStart| {
+            // Keep the page from scrolling or zooming instead
+            event.prevent_default();
+            let process_event = &mut *process_event.lock().unwrap();
+            match event.touches().as_slice() {
+                [touch] => {
+                    let point = Point::from_data(touch.client_x(), touch.client_y());
+                    process_event(GameEvent::SwipeStart(
+                        point,
+                        event.time_stamp().unwrap_or(0.0),
+                    ));
+                }
+                // Pinches and other gestures with more fingers aren't swipes
+                _ => process_event(GameEvent::SwipeCancel),
+            }
+        }
+    });
+
+    // Add event handler TouchMove
+    board.add_event_listener(|event: event::TouchMove| {
+        event.prevent_default();
+    });
+
+    // Add event handler TouchEnd
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::TouchEnd| {
+            event.prevent_default();
+            if let Some(touch) = event.changed_touches().first() {
+                let point = Point::from_data(touch.client_x(), touch.client_y());
                 let process_event = &mut *process_event.lock().unwrap();
-                process_event(GameEvent::MouseDown(event));
+                process_event(GameEvent::SwipeEnd(
+                    point,
+                    event.time_stamp().unwrap_or(0.0),
+                ));
             }
-        });
+        }
+    });
 
-    // Add event handler MouseUp
+    // Add event handler TouchCancel
+    board.add_event_listener({
+        let process_event = process_event.clone();
+        move |_event: event::TouchCancel| {
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::SwipeCancel);
+        }
+    });
+
+    // Add event handler KeyDown
+    document().add_event_listener({
+        let process_event = process_event.clone();
+        move |event: event::KeyDownEvent| {
+            let process_event = &mut *process_event.lock().unwrap();
+            process_event(GameEvent::KeyDown(event));
+        }
+    });
+
+    // Add event handler for the board shape picker
+    size_picker().add_event_listener({
+        let process_event = process_event.clone();
+        move |_event: event::ChangeEvent| {
+            let picker = size_picker();
+            // Let the arrow keys move tiles again instead of the selection
+            picker.blur();
+            if let Some((width, height)) = picker.value().as_deref().and_then(parse_shape) {
+                let process_event = &mut *process_event.lock().unwrap();
+                process_event(GameEvent::Shape(width, height));
+            }
+        }
+    });
+
+    // Add event handler for the hint button
     document()
-        .get_element_by_id("canvas")
+        .get_element_by_id("hint")
         .unwrap()
         .add_event_listener({
             let process_event = process_event.clone();
-            move |event: event::MouseUpEvent| {
+            move |_event: event::ClickEvent| {
                 let process_event = &mut *process_event.lock().unwrap();
-                event.prevent_default();
-                process_event(GameEvent::MouseUp(event));
+                process_event(GameEvent::Hint);
             }
         });
 
-    // Add event handler KeyDown
-    document().add_event_listener({
+    // Add event handler for the strategy picker
+    strategy_picker().add_event_listener({
         let process_event = process_event.clone();
-        move |event: event::KeyDownEvent| {
+        move |_event: event::ChangeEvent| {
+            let picker = strategy_picker();
+            picker.blur();
+            if let Some(name) = picker.value() {
+                let process_event = &mut *process_event.lock().unwrap();
+                process_event(GameEvent::Strategy(name));
+            }
+        }
+    });
+
+    // Add event handler for the theme picker
+    theme::picker().add_event_listener({
+        let process_event = process_event.clone();
+        move |_event: event::ChangeEvent| {
+            let picker = theme::picker();
+            picker.blur();
+            let picked = match picker.value() {
+                Some(ref name) if name != theme::LOAD_CUSTOM => storage::load_themes()
+                    .into_iter()
+                    .find(|theme| &theme.name == name),
+                _ => theme::ask_custom().inspect(storage::save_theme),
+            };
+            match picked {
+                Some(picked) => {
+                    theme::fill_picker(&storage::load_themes(), &picked.name);
+                    let process_event = &mut *process_event.lock().unwrap();
+                    process_event(GameEvent::Theme(Box::new(picked)));
+                }
+                None => {
+                    // Show the theme in use again
+                    let name = storage::load_theme_name();
+                    let _ = picker.set_value(Some(name.as_deref().unwrap_or(BUILTIN[0])));
+                }
+            }
+        }
+    });
+
+    // Add event handler Resize
+    window().add_event_listener({
+        let process_event = process_event.clone();
+        move |_event: event::ResizeEvent| {
             let process_event = &mut *process_event.lock().unwrap();
-            process_event(GameEvent::KeyDown(event));
+            process_event(GameEvent::Resize);
         }
     });
 
@@ -146,6 +863,18 @@ fn main() {
         }
     });
 
+    // Drive animations from the browser's repaint loop
+    fn run_animation_loop(process_event: Arc<Mutex<dyn FnMut(GameEvent)>>) {
+        window().request_animation_frame(move |timestamp| {
+            {
+                let process_event_fn = &mut *process_event.lock().unwrap();
+                process_event_fn(GameEvent::Frame(timestamp));
+            }
+            run_animation_loop(process_event);
+        });
+    }
+    run_animation_loop(process_event.clone());
+
     // Set up and start a timer if needed.
     if TICK_MS.is_some() {
         fn run_timer(process_event: Arc<Mutex<dyn FnMut(GameEvent)>>) {
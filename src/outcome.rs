@@ -0,0 +1,41 @@
+//! A structured report of what a single move did to the board.
+
+/// A tile that moved from one cell to another without merging
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Slide {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// Two tiles that slid into `target` and merged into a tile of `value`
+///
+/// The sources are the cells the tiles started the move in. A tile that
+/// didn't move before being merged into has its own cell as source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Merge {
+    pub sources: [(usize, usize); 2],
+    pub target: (usize, usize),
+    pub value: usize,
+}
+
+/// A new tile of `value` placed at `position` after a move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spawn {
+    pub position: (usize, usize),
+    pub value: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub slides: Vec<Slide>,
+    pub merges: Vec<Merge>,
+    pub score_delta: usize,
+    pub spawn: Option<Spawn>,
+}
+
+impl MoveOutcome {
+    /// Whether any tile moved or merged, i.e. the move was legal
+    pub fn moved(&self) -> bool {
+        !self.slides.is_empty() || !self.merges.is_empty()
+    }
+}
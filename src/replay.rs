@@ -0,0 +1,438 @@
+//! Recording games and playing them back.
+//!
+//! A `Replay` is the position a game started from plus every move made
+//! since. Since spawns are drawn from the game's seeded generator, replaying
+//! the moves from the starting position reproduces the game exactly. Only
+//! games with a generator that can't be saved record their spawns too.
+//!
+//! The compact binary form is laid out as
+//!
+//! | field     | encoding                                                |
+//! |-----------|---------------------------------------------------------|
+//! | version   | byte                                                    |
+//! | flags     | byte, 1 if spawns are recorded                          |
+//! | start     | varint length followed by the binary `Position`         |
+//! | moves     | varint count followed by 2 bits per move, 4 per byte    |
+//! | spawns    | per move a varint cell index and the tile's exponent    |
+//!
+//! Varints are unsigned LEB128 and moves are packed starting with the
+//! lowest bits.
+
+use crate::encoding::{self, Reader};
+use crate::game::{Direction, Game};
+use crate::outcome::{MoveOutcome, Spawn};
+use crate::save::{self, LoadError, Position};
+
+/// Current version of the replay format
+pub const VERSION: u8 = 1;
+
+const FLAG_SPAWNS: u8 = 1;
+
+fn encode_direction(direction: Direction) -> u8 {
+    match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
+
+fn decode_direction(bits: u8) -> Direction {
+    match bits & 0b11 {
+        0 => Direction::Up,
+        1 => Direction::Down,
+        2 => Direction::Left,
+        _ => Direction::Right,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    /// The game before the first move
+    pub start: Position,
+    pub moves: Vec<Direction>,
+    /// The tile spawned after each move, only recorded if the game's
+    /// generator can't be saved
+    pub spawns: Option<Vec<Spawn>>,
+}
+
+impl Replay {
+    /// Write the compact binary form described in the module docs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![VERSION];
+        bytes.push(match self.spawns {
+            Some(_) => FLAG_SPAWNS,
+            None => 0,
+        });
+
+        let start = self.start.to_bytes();
+        encoding::write_varint(&mut bytes, start.len() as u64);
+        bytes.extend(start);
+
+        encoding::write_varint(&mut bytes, self.moves.len() as u64);
+        for chunk in self.moves.chunks(4) {
+            bytes.push(chunk.iter().enumerate().fold(0, |byte, (i, &direction)| {
+                byte | encode_direction(direction) << (2 * i)
+            }));
+        }
+
+        if let Some(spawns) = &self.spawns {
+            for spawn in spawns {
+                let (x, y) = spawn.position;
+                encoding::write_varint(&mut bytes, (y * self.start.width + x) as u64);
+                bytes.push(spawn.value.trailing_zeros() as u8);
+            }
+        }
+        bytes
+    }
+
+    /// Read the compact binary form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.read_u8()?;
+        if version == 0 || version > VERSION {
+            return Err(LoadError::UnsupportedVersion(u64::from(version)));
+        }
+        let flags = reader.read_u8()?;
+
+        let length = reader.read_usize()?;
+        let start = (0..length)
+            .map(|_| reader.read_u8())
+            .collect::<Result<Vec<u8>, _>>()?;
+        let start = Position::from_bytes(&start)?;
+
+        let count = reader.read_usize()?;
+        let mut moves = Vec::new();
+        for i in 0..count {
+            if i % 4 == 0 {
+                let byte = reader.read_u8()?;
+                let packed = (count - i).min(4);
+                moves.extend((0..packed).map(|j| decode_direction(byte >> (2 * j))));
+            }
+        }
+
+        let spawns = match flags & FLAG_SPAWNS {
+            0 => None,
+            _ => {
+                let mut spawns = Vec::new();
+                for _ in 0..count {
+                    let cell = reader.read_usize()?;
+                    let value = match reader.read_u8()? {
+                        exponent if exponent > 0 && u32::from(exponent) < usize::BITS => {
+                            1 << exponent
+                        }
+                        exponent => return Err(LoadError::InvalidExponent(exponent)),
+                    };
+                    let width = start.width.max(1);
+                    let position = (cell % width, cell / width);
+                    save::validate_position(start.width, start.height, Some(position))?;
+                    spawns.push(Spawn { position, value });
+                }
+                Some(spawns)
+            }
+        };
+        reader.finish()?;
+
+        Ok(Replay {
+            start,
+            moves,
+            spawns,
+        })
+    }
+
+    /// The binary form as URL-safe base64
+    pub fn to_base64(&self) -> String {
+        encoding::to_base64(&self.to_bytes())
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, LoadError> {
+        Replay::from_bytes(&encoding::from_base64(encoded)?)
+    }
+}
+
+/// Builds a replay while a game is played
+///
+/// Frontends pass every move made to `record` and mirror the game's undo
+/// and redo, so the replay always follows the line that was actually
+/// played.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    replay: Replay,
+    undone: Vec<(Direction, Option<Spawn>)>,
+}
+
+impl Recorder {
+    /// Start recording from `game`'s current position
+    pub fn new(game: &Game) -> Self {
+        Recorder {
+            replay: Replay {
+                start: game.position(),
+                moves: Vec::new(),
+                spawns: match game.can_save_rng() {
+                    true => None,
+                    false => Some(Vec::new()),
+                },
+            },
+            undone: Vec::new(),
+        }
+    }
+
+    /// Continue recording an existing replay
+    pub fn resume(replay: Replay) -> Self {
+        Recorder {
+            replay,
+            undone: Vec::new(),
+        }
+    }
+
+    /// Add a move, illegal moves are skipped
+    pub fn record(&mut self, direction: Direction, outcome: &MoveOutcome) {
+        if !outcome.moved() {
+            return;
+        }
+        self.undone.clear();
+        self.replay.moves.push(direction);
+        if let (Some(spawns), Some(spawn)) = (&mut self.replay.spawns, outcome.spawn) {
+            spawns.push(spawn);
+        }
+    }
+
+    /// Drop the last move after it was undone in the game
+    pub fn undo(&mut self) {
+        if let Some(direction) = self.replay.moves.pop() {
+            let spawn = self.replay.spawns.as_mut().and_then(|spawns| spawns.pop());
+            self.undone.push((direction, spawn));
+        }
+    }
+
+    /// Add the last undone move again after it was redone in the game
+    pub fn redo(&mut self) {
+        if let Some((direction, spawn)) = self.undone.pop() {
+            self.replay.moves.push(direction);
+            if let (Some(spawns), Some(spawn)) = (&mut self.replay.spawns, spawn) {
+                spawns.push(spawn);
+            }
+        }
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+/// Steps through a replay, driving a `Game` with the recorded moves
+pub struct Player {
+    replay: Replay,
+    game: Game,
+    index: usize,
+}
+
+impl Player {
+    /// Set up the starting position, checking that the replay is complete
+    pub fn new(replay: Replay) -> Result<Self, LoadError> {
+        if let Some(spawns) = &replay.spawns {
+            if spawns.len() != replay.moves.len() {
+                return Err(LoadError::MissingSpawns {
+                    moves: replay.moves.len(),
+                    spawns: spawns.len(),
+                });
+            }
+        }
+        let game = Game::from_position(replay.start.clone())?;
+        Ok(Player {
+            replay,
+            game,
+            index: 0,
+        })
+    }
+
+    /// The game as of the current move
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of moves played so far
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Number of moves in the replay
+    pub fn len(&self) -> usize {
+        self.replay.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.moves.is_empty()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.index == self.len()
+    }
+
+    /// Play the next move, `None` at the end of the replay
+    pub fn step_forward(&mut self) -> Option<MoveOutcome> {
+        let direction = *self.replay.moves.get(self.index)?;
+        let mut outcome = self.game.shift(&direction);
+        if outcome.moved() {
+            outcome.spawn = match &self.replay.spawns {
+                Some(spawns) => {
+                    self.game.place_tile(spawns[self.index]);
+                    Some(spawns[self.index])
+                }
+                None => self.game.seed_cell(),
+            };
+        }
+        self.index += 1;
+        Some(outcome)
+    }
+
+    /// Take back the last move, returns false at the start of the replay
+    pub fn step_back(&mut self) -> bool {
+        if self.index == 0 {
+            return false;
+        }
+        if self.game.undo() {
+            self.index -= 1;
+        } else {
+            // older than the game's history, play up to it from the start
+            self.seek(self.index - 1);
+        }
+        true
+    }
+
+    /// Jump to the position after `index` moves
+    pub fn seek(&mut self, index: usize) {
+        if index < self.index {
+            self.game = Game::from_position(self.replay.start.clone())
+                .expect("the start position was checked in Player::new");
+            self.index = 0;
+        }
+        while self.index < index && self.step_forward().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Rng, SplitMix64};
+
+    /// A generator whose state can't be saved, so spawns get recorded
+    #[derive(Clone, Debug)]
+    struct Unsaved(SplitMix64);
+
+    impl Rng for Unsaved {
+        fn next_u64(&mut self) -> u64 {
+            self.0.next_u64()
+        }
+
+        fn boxed_clone(&self) -> Box<dyn Rng> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// Play a few dozen moves on `game` with an undo and a redo along the
+    /// way, returning the game and its recording
+    fn record(mut game: Game) -> (Game, Recorder) {
+        game.seed_cell();
+        game.seed_cell();
+        let mut recorder = Recorder::new(&game);
+        for (i, &direction) in Direction::ALL.iter().cycle().take(60).enumerate() {
+            let outcome = game.play(&direction);
+            recorder.record(direction, &outcome);
+            if i == 30 {
+                game.undo();
+                recorder.undo();
+                game.redo();
+                recorder.redo();
+            }
+        }
+        (game, recorder)
+    }
+
+    fn play_back(replay: Replay) -> Player {
+        let mut player = Player::new(replay).unwrap();
+        player.seek(usize::MAX);
+        player
+    }
+
+    #[test]
+    fn replays_games_exactly() {
+        let mut unsaved = Game::from_shape(5, 4, 0);
+        unsaved.set_rng(Box::new(Unsaved(SplitMix64::from_seed(3))));
+        for game in [Game::from_shape(4, 4, 11), unsaved] {
+            let (game, recorder) = record(game);
+            let replay = recorder.get_replay();
+            assert_eq!(replay.spawns.is_some(), !game.can_save_rng());
+            assert_eq!(replay.moves.len(), game.get_moves());
+
+            let decoded = Replay::from_base64(&replay.to_base64()).unwrap();
+            assert_eq!(&decoded, replay);
+            let player = play_back(decoded);
+            assert!(player.is_at_end());
+            assert_eq!(player.get_game().get_states(), game.get_states());
+            assert_eq!(player.get_game().get_score(), game.get_score());
+        }
+    }
+
+    #[test]
+    fn steps_back_and_seeks() {
+        let (_, recorder) = record(Game::from_shape(4, 4, 5));
+        let mut player = Player::new(recorder.get_replay().clone()).unwrap();
+        assert!(!player.step_back());
+        let mut boards = vec![player.get_game().get_states()];
+        while player.step_forward().is_some() {
+            boards.push(player.get_game().get_states());
+        }
+        assert!(player.is_at_end());
+        assert!(player.step_forward().is_none());
+        assert_eq!(boards.len(), player.len() + 1);
+
+        for index in (0..player.len()).rev() {
+            assert!(player.step_back());
+            assert_eq!(player.get_index(), index);
+            assert_eq!(player.get_game().get_states(), boards[index]);
+        }
+        assert!(!player.step_back());
+        player.seek(10);
+        assert_eq!(player.get_game().get_states(), boards[10]);
+        player.seek(3);
+        assert_eq!(player.get_game().get_states(), boards[3]);
+    }
+
+    #[test]
+    fn rejects_corrupt_replays() {
+        let (_, recorder) = record(Game::from_shape(4, 4, 9));
+        let bytes = recorder.get_replay().to_bytes();
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(LoadError::UnexpectedEnd)
+        ));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(matches!(
+            Replay::from_bytes(&longer),
+            Err(LoadError::TrailingData)
+        ));
+        let mut newer = bytes.clone();
+        newer[0] = VERSION + 1;
+        assert!(matches!(
+            Replay::from_bytes(&newer),
+            Err(LoadError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            Replay::from_base64("not base64!"),
+            Err(LoadError::InvalidBase64)
+        ));
+
+        let mut replay = recorder.get_replay().clone();
+        replay.spawns = Some(Vec::new());
+        assert!(matches!(
+            Player::new(replay),
+            Err(LoadError::MissingSpawns { spawns: 0, .. })
+        ));
+    }
+}
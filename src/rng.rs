@@ -0,0 +1,115 @@
+//! Random number generation for spawning tiles.
+//!
+//! Games draw all randomness from a [`Rng`] so a game started from a known
+//! seed replays exactly. The default generator is [`SplitMix64`], which is
+//! tiny, fast and good enough for picking cells.
+
+use std::fmt::Debug;
+
+pub trait Rng: Debug + Send {
+    /// Return the next 64 random bits
+    fn next_u64(&mut self) -> u64;
+
+    /// Clone into a new box so games holding an `Rng` can be cloned
+    fn boxed_clone(&self) -> Box<dyn Rng>;
+
+    /// Internal state for saving a game, generators that can't be saved
+    /// return `None`
+    ///
+    /// A saved state is resumed with `SplitMix64::from_seed`.
+    fn get_state(&self) -> Option<u64> {
+        None
+    }
+
+    /// Return a uniformly distributed number in `0..n`
+    ///
+    /// Uses rejection sampling so small ranges aren't biased like a plain
+    /// modulo would be.
+    fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "cannot sample from an empty range");
+        let n = n as u64;
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+}
+
+impl Clone for Box<dyn Rng> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// The SplitMix64 generator by Sebastiano Vigna
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn from_seed(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Rng> {
+        Box::new(self.clone())
+    }
+
+    fn get_state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, Game};
+
+    #[test]
+    fn same_seed_spawns_the_same_tiles() {
+        let play = |seed| {
+            let mut game = Game::from_shape(4, 4, seed);
+            let mut spawns = vec![game.seed_cell(), game.seed_cell()];
+            for direction in Direction::ALL.iter().cycle().take(200) {
+                spawns.push(game.play(direction).spawn);
+            }
+            (spawns, game.get_states(), game.get_score())
+        };
+        assert_eq!(play(42), play(42));
+        assert_ne!(play(42), play(43));
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut rng = SplitMix64::from_seed(1);
+        for n in 1..50 {
+            let mut seen = vec![false; n];
+            for _ in 0..20 * n {
+                let value = rng.below(n);
+                assert!(value < n);
+                seen[value] = true;
+            }
+            assert!(
+                seen.iter().all(|&seen| seen),
+                "not every value below {} came up",
+                n
+            );
+        }
+        let value = rng.below(usize::MAX);
+        assert!(value < usize::MAX);
+    }
+}
@@ -0,0 +1,532 @@
+//! Saving, resuming and sharing games.
+//!
+//! `Game::save` captures everything needed to pick a game up again (board,
+//! scores, flags, the random number generator and the undo history) as a
+//! `SavedGame`, which is stored as JSON. Every save carries the schema
+//! version it was written with, so saves from older versions can be
+//! migrated when the format changes instead of being thrown away.
+//!
+//! `Game::position` captures the same without the history. A `Position`
+//! can also be written in a compact binary form, which encoded as base64
+//! is short enough to share in a URL:
+//!
+//! | field                    | encoding                                |
+//! |--------------------------|-----------------------------------------|
+//! | version                  | byte                                    |
+//! | flags                    | byte, see the `FLAG_` constants         |
+//! | width, height            | varints                                 |
+//! | score, best, target      | varints                                 |
+//! | moves                    | varint                                  |
+//! | seed                     | varint                                  |
+//! | rng state                | varint, only if `FLAG_RNG_STATE` is set |
+//! | board                    | one byte per cell, the tile's exponent  |
+//!
+//! Varints are unsigned LEB128 and empty cells have exponent 0. Version 1
+//! only had square boards and stored a single size instead of the width
+//! and height, versions before 3 didn't count moves.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::encoding::{self, Reader};
+
+/// Current version of the save format
+pub const VERSION: u64 = 3;
+
+const FLAG_FINISHED: u8 = 1;
+const FLAG_WON: u8 = 1 << 1;
+const FLAG_KEEP_PLAYING: u8 = 1 << 2;
+const FLAG_RNG_STATE: u8 = 1 << 3;
+
+/// Everything about a game except its undo history
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub version: u64,
+    pub width: usize,
+    pub height: usize,
+    pub board: Vec<usize>,
+    pub score: usize,
+    pub best: usize,
+    pub moves: usize,
+    pub target: usize,
+    pub finished: bool,
+    pub won: bool,
+    pub keep_playing: bool,
+    pub seed: u64,
+    /// `None` if the game used a generator that can't be saved, it will
+    /// be resumed from `seed` instead
+    pub rng_state: Option<u64>,
+}
+
+impl Position {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a position is always valid JSON")
+    }
+
+    /// Parse a position, migrating it from older versions of the format
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        serde_json::from_value(parse_json(json)?).map_err(LoadError::Json)
+    }
+
+    /// Write the compact binary form described in the module docs
+    ///
+    /// Panics if a tile isn't 0 or a power of two, which a `Position` taken
+    /// from a `Game` never has.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![VERSION as u8];
+        let mut flags = 0;
+        for &(set, flag) in &[
+            (self.finished, FLAG_FINISHED),
+            (self.won, FLAG_WON),
+            (self.keep_playing, FLAG_KEEP_PLAYING),
+            (self.rng_state.is_some(), FLAG_RNG_STATE),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        bytes.push(flags);
+        for &value in &[
+            self.width,
+            self.height,
+            self.score,
+            self.best,
+            self.target,
+            self.moves,
+        ] {
+            encoding::write_varint(&mut bytes, value as u64);
+        }
+        encoding::write_varint(&mut bytes, self.seed);
+        if let Some(state) = self.rng_state {
+            encoding::write_varint(&mut bytes, state);
+        }
+        for &value in &self.board {
+            assert!(value != 1 && value.is_power_of_two() || value == 0);
+            bytes.push(match value {
+                0 => 0,
+                _ => value.trailing_zeros() as u8,
+            });
+        }
+        bytes
+    }
+
+    /// Read the compact binary form, checking the board's shape
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = Reader::new(bytes);
+        let version = u64::from(reader.read_u8()?);
+        if version == 0 || version > VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let flags = reader.read_u8()?;
+        let width = reader.read_usize()?;
+        let height = match version {
+            1 => width,
+            _ => reader.read_usize()?,
+        };
+        let score = reader.read_usize()?;
+        let best = reader.read_usize()?;
+        let target = reader.read_usize()?;
+        let moves = match version {
+            1 | 2 => 0,
+            _ => reader.read_usize()?,
+        };
+        let seed = reader.read_varint()?;
+        let rng_state = match flags & FLAG_RNG_STATE {
+            0 => None,
+            _ => Some(reader.read_varint()?),
+        };
+        let cells = width.checked_mul(height).ok_or(LoadError::Overflow)?;
+        let mut board = Vec::new();
+        for _ in 0..cells {
+            board.push(match reader.read_u8()? {
+                0 => 0,
+                exponent if u32::from(exponent) < usize::BITS => 1 << exponent,
+                exponent => return Err(LoadError::InvalidExponent(exponent)),
+            });
+        }
+        reader.finish()?;
+        Ok(Position {
+            version: VERSION,
+            width,
+            height,
+            board,
+            score,
+            best,
+            moves,
+            target,
+            finished: flags & FLAG_FINISHED != 0,
+            won: flags & FLAG_WON != 0,
+            keep_playing: flags & FLAG_KEEP_PLAYING != 0,
+            seed,
+            rng_state,
+        })
+    }
+
+    /// The binary form as URL-safe base64
+    pub fn to_base64(&self) -> String {
+        encoding::to_base64(&self.to_bytes())
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, LoadError> {
+        Position::from_bytes(&encoding::from_base64(encoded)?)
+    }
+}
+
+/// A single state in the undo history
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedState {
+    pub board: Vec<usize>,
+    pub score: usize,
+    pub finished: bool,
+    pub won: bool,
+    pub spawned: Option<(usize, usize)>,
+    pub rng_state: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGame {
+    #[serde(flatten)]
+    pub position: Position,
+    pub spawned: Option<(usize, usize)>,
+    pub history_capacity: usize,
+    pub undo_budget: Option<usize>,
+    pub undos_used: usize,
+    /// States that can be undone, oldest first
+    pub undo: Vec<SavedState>,
+    /// States that can be redone, the next one to redo last
+    pub redo: Vec<SavedState>,
+}
+
+impl SavedGame {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a saved game is always valid JSON")
+    }
+
+    /// Parse a save, migrating it from older versions of the format
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        serde_json::from_value(parse_json(json)?).map_err(LoadError::Json)
+    }
+}
+
+/// Parse JSON and bring it up to the current version of the format
+fn parse_json(json: &str) -> Result<Value, LoadError> {
+    let mut value: Value = serde_json::from_str(json).map_err(LoadError::Json)?;
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(LoadError::MissingVersion)?;
+    migrate(&mut value, version)?;
+    Ok(value)
+}
+
+/// Upgrade a save written with format `version` to the current one
+///
+/// Each change to the format bumps `VERSION` and adds a step here that
+/// rewrites a save of the previous version.
+fn migrate(value: &mut Value, mut version: u64) -> Result<(), LoadError> {
+    if version == 0 || version > VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+    while version < VERSION {
+        if version == 1 {
+            // square boards only had a size
+            let size = value
+                .as_object_mut()
+                .and_then(|object| object.remove("size"))
+                .unwrap_or(Value::Null);
+            value["width"] = size.clone();
+            value["height"] = size;
+        }
+        if version == 2 {
+            value["moves"] = Value::from(0);
+        }
+        version += 1;
+        value["version"] = Value::from(version);
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The save isn't valid JSON or doesn't match the format
+    Json(serde_json::Error),
+    MissingVersion,
+    /// The save was written by an unknown version of the format
+    UnsupportedVersion(u64),
+    /// The board doesn't have `width * height` cells
+    InvalidBoard {
+        width: usize,
+        height: usize,
+        cells: usize,
+    },
+    /// A cell holds something other than 0 or a power of two
+    InvalidTile(usize),
+    /// A position lies outside of the board
+    InvalidPosition(usize, usize),
+    /// The base64 form contains an invalid character or length
+    InvalidBase64,
+    /// The binary form ends in the middle of a field
+    UnexpectedEnd,
+    /// The binary form has bytes left after the board
+    TrailingData,
+    /// A number in the binary form doesn't fit its field
+    Overflow,
+    /// A tile in the binary form is too large to represent
+    InvalidExponent(u8),
+    /// A replay with recorded spawns doesn't have one for every move
+    MissingSpawns {
+        moves: usize,
+        spawns: usize,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoadError::Json(error) => write!(f, "malformed save: {}", error),
+            LoadError::MissingVersion => write!(f, "save has no version"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+            LoadError::InvalidBoard {
+                width,
+                height,
+                cells,
+            } => write!(
+                f,
+                "{}x{} board needs {} cells, found {}",
+                width,
+                height,
+                width.saturating_mul(*height),
+                cells
+            ),
+            LoadError::InvalidTile(value) => write!(f, "invalid tile {}", value),
+            LoadError::InvalidPosition(x, y) => write!(f, "({},{}) is out of bounds", x, y),
+            LoadError::InvalidBase64 => write!(f, "invalid base64"),
+            LoadError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            LoadError::TrailingData => write!(f, "unexpected data after the board"),
+            LoadError::Overflow => write!(f, "number out of range"),
+            LoadError::InvalidExponent(exponent) => write!(f, "invalid tile 2^{}", exponent),
+            LoadError::MissingSpawns { moves, spawns } => {
+                write!(f, "replay has {} moves but {} spawns", moves, spawns)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Check that `board` is a valid board of `width` by `height` cells
+pub fn validate_board(width: usize, height: usize, board: &[usize]) -> Result<(), LoadError> {
+    if width == 0 || height == 0 || width.checked_mul(height) != Some(board.len()) {
+        return Err(LoadError::InvalidBoard {
+            width,
+            height,
+            cells: board.len(),
+        });
+    }
+    match board
+        .iter()
+        .find(|&&value| value != 0 && (value == 1 || !value.is_power_of_two()))
+    {
+        Some(&value) => Err(LoadError::InvalidTile(value)),
+        None => Ok(()),
+    }
+}
+
+/// Check that `position` lies on a board of `width` by `height` cells
+pub fn validate_position(
+    width: usize,
+    height: usize,
+    position: Option<(usize, usize)>,
+) -> Result<(), LoadError> {
+    match position {
+        Some((x, y)) if x >= width || y >= height => Err(LoadError::InvalidPosition(x, y)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, Game};
+
+    /// A 5x4 game a few moves in, with something to undo and redo
+    fn played() -> Game {
+        let mut game = Game::from_shape(5, 4, 7);
+        game.seed_cell();
+        game.seed_cell();
+        for direction in Direction::ALL.iter().cycle().take(20) {
+            game.play(direction);
+        }
+        game.undo();
+        game.undo();
+        game.redo();
+        game
+    }
+
+    /// `json` as the given older version of the format would have had it
+    fn downgrade(json: &str, version: u64) -> String {
+        let mut value: Value = serde_json::from_str(json).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("moves");
+        if version == 1 {
+            let width = object.remove("width").unwrap();
+            object.remove("height");
+            object.insert("size".to_owned(), width);
+        }
+        object.insert("version".to_owned(), Value::from(version));
+        value.to_string()
+    }
+
+    #[test]
+    fn position_round_trips() {
+        let position = played().position();
+        assert_eq!(Position::from_json(&position.to_json()).unwrap(), position);
+        assert_eq!(
+            Position::from_bytes(&position.to_bytes()).unwrap(),
+            position
+        );
+        assert_eq!(
+            Position::from_base64(&position.to_base64()).unwrap(),
+            position
+        );
+    }
+
+    #[test]
+    fn saved_game_round_trips() {
+        let saved = played().save();
+        assert!(!saved.undo.is_empty() && !saved.redo.is_empty());
+        let loaded = SavedGame::from_json(&saved.to_json()).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(Game::load(loaded).unwrap().save(), saved);
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let mut game = Game::from_seed(4, 3);
+        game.seed_cell();
+        game.play(&Direction::Left);
+        let saved = game.save();
+        let migrated = SavedGame::from_json(&downgrade(&saved.to_json(), 1)).unwrap();
+        assert_eq!(migrated.position.version, VERSION);
+        assert_eq!((migrated.position.width, migrated.position.height), (4, 4));
+        assert_eq!(migrated.position.moves, 0);
+        assert_eq!(migrated.position.board, saved.position.board);
+
+        let position = game.position();
+        let migrated = Position::from_json(&downgrade(&position.to_json(), 1)).unwrap();
+        assert_eq!((migrated.width, migrated.height), (4, 4));
+    }
+
+    #[test]
+    fn migrates_version_2() {
+        let saved = played().save();
+        let migrated = SavedGame::from_json(&downgrade(&saved.to_json(), 2)).unwrap();
+        assert_eq!(migrated.position.version, VERSION);
+        assert_eq!(migrated.position.moves, 0);
+        assert_eq!(
+            SavedGame {
+                position: Position {
+                    moves: saved.position.moves,
+                    ..migrated.position.clone()
+                },
+                ..migrated
+            },
+            saved
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let json = played().position().to_json();
+        let mut value: Value = serde_json::from_str(&json).unwrap();
+        value["version"] = Value::from(VERSION + 1);
+        assert!(matches!(
+            Position::from_json(&value.to_string()),
+            Err(LoadError::UnsupportedVersion(version)) if version == VERSION + 1
+        ));
+        value.as_object_mut().unwrap().remove("version");
+        assert!(matches!(
+            Position::from_json(&value.to_string()),
+            Err(LoadError::MissingVersion)
+        ));
+        assert!(matches!(Position::from_json("{"), Err(LoadError::Json(_))));
+    }
+
+    #[test]
+    fn rejects_truncated_bytes() {
+        let bytes = played().position().to_bytes();
+        for end in 0..bytes.len() {
+            assert!(matches!(
+                Position::from_bytes(&bytes[..end]),
+                Err(LoadError::UnexpectedEnd)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = played().position().to_bytes();
+        bytes.push(0);
+        assert!(matches!(
+            Position::from_bytes(&bytes),
+            Err(LoadError::TrailingData)
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_exponents() {
+        let mut bytes = played().position().to_bytes();
+        *bytes.last_mut().unwrap() = 200;
+        assert!(matches!(
+            Position::from_bytes(&bytes),
+            Err(LoadError::InvalidExponent(200))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_base64() {
+        let encoded = played().position().to_base64();
+        assert!(matches!(
+            Position::from_base64(&format!("{}!", &encoded[1..])),
+            Err(LoadError::InvalidBase64)
+        ));
+        assert!(matches!(
+            Position::from_base64("AAAAA"),
+            Err(LoadError::InvalidBase64)
+        ));
+    }
+
+    #[test]
+    fn rejects_boards_of_the_wrong_length() {
+        let mut position = played().position();
+        position.board.pop();
+        let position = Position::from_json(&position.to_json()).unwrap();
+        assert!(matches!(
+            Game::from_position(position),
+            Err(LoadError::InvalidBoard {
+                width: 5,
+                height: 4,
+                cells: 19
+            })
+        ));
+
+        let mut saved = played().save();
+        saved.undo[0].board.push(0);
+        assert!(matches!(
+            Game::load(saved),
+            Err(LoadError::InvalidBoard { cells: 21, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_tiles() {
+        let mut position = played().position();
+        position.board[0] = 3;
+        assert!(matches!(
+            Game::from_position(position),
+            Err(LoadError::InvalidTile(3))
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+//! High scores, kept separately for each variant of the game.
+//!
+//! A record on a 3x3 board says nothing about one on a 6x6 board, so
+//! `HighScores` keeps the best results of each `Variant` apart. Frontends
+//! submit a result once a game ends and store the table as JSON.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::game::{self, Game};
+use crate::save::LoadError;
+
+/// Current version of the high score format
+pub const VERSION: u64 = 1;
+/// Number of results kept for each variant by default
+pub const DEFAULT_CAPACITY: usize = 10;
+
+/// The rules a game is played under
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Variant {
+    pub width: usize,
+    pub height: usize,
+    pub target: usize,
+    /// Chance in percent that a spawned tile is a 4
+    pub four_chance: usize,
+    /// Seconds allowed per game, `None` for untimed games
+    pub time_limit: Option<u64>,
+}
+
+impl Variant {
+    /// The variant `game` is played under
+    pub fn of(game: &Game) -> Self {
+        Variant {
+            width: game.get_width(),
+            height: game.get_height(),
+            target: game.get_target_tile(),
+            four_chance: game::FOUR_CHANCE,
+            time_limit: None,
+        }
+    }
+
+    /// A short name like "4x4 2048" for showing the variant, spawn rules
+    /// and time limits are only mentioned if they aren't the usual ones
+    pub fn name(&self) -> String {
+        let mut name = format!("{}x{} {}", self.width, self.height, self.target);
+        if self.four_chance != game::FOUR_CHANCE {
+            name.push_str(&format!(" {}% fours", self.four_chance));
+        }
+        if let Some(limit) = self.time_limit {
+            name.push_str(&format!(" {}s", limit));
+        }
+        name
+    }
+
+    /// Key the variant's results are stored under
+    fn key(&self) -> String {
+        let time = match self.time_limit {
+            Some(limit) => limit.to_string(),
+            None => "untimed".to_owned(),
+        };
+        format!(
+            "{}x{}-{}-{}-{}",
+            self.width, self.height, self.target, self.four_chance, time
+        )
+    }
+}
+
+/// A finished game in the table
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub score: usize,
+    pub moves: usize,
+    pub max_tile: usize,
+    /// When the game ended, in seconds since the Unix epoch
+    pub date: u64,
+}
+
+impl Score {
+    /// The result of `game` as of `date`
+    pub fn of(game: &Game, date: u64) -> Self {
+        Score {
+            score: game.get_score(),
+            moves: game.get_moves(),
+            max_tile: game.get_max_tile(),
+            date,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    variant: Variant,
+    /// Best first
+    scores: Vec<Score>,
+}
+
+/// The best results of each variant
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    version: u64,
+    capacity: usize,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl HighScores {
+    /// An empty table keeping the best `capacity` results of each variant
+    pub fn with_capacity(capacity: usize) -> Self {
+        HighScores {
+            version: VERSION,
+            capacity,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Add a result, returns its rank (0 being the best) if it made the
+    /// table
+    ///
+    /// Games without any points are left out. Ties go to the earlier game.
+    pub fn submit(&mut self, variant: Variant, score: Score) -> Option<usize> {
+        if score.score == 0 || self.capacity == 0 {
+            return None;
+        }
+        let scores = &mut self
+            .entries
+            .entry(variant.key())
+            .or_insert_with(|| Entry {
+                variant,
+                scores: Vec::new(),
+            })
+            .scores;
+        let rank = scores
+            .iter()
+            .position(|other| other.score < score.score)
+            .unwrap_or(scores.len());
+        if rank >= self.capacity {
+            return None;
+        }
+        scores.insert(rank, score);
+        scores.truncate(self.capacity);
+        Some(rank)
+    }
+
+    /// Take back a result submitted earlier, e.g. of a game that was undone
+    /// to carry on, returns whether it was in the table
+    ///
+    /// Results it pushed out of the table don't come back.
+    pub fn withdraw(&mut self, variant: &Variant, score: &Score) -> bool {
+        let key = variant.key();
+        let scores = match self.entries.get_mut(&key) {
+            Some(entry) => &mut entry.scores,
+            None => return false,
+        };
+        let rank = match scores.iter().position(|other| other == score) {
+            Some(rank) => rank,
+            None => return false,
+        };
+        scores.remove(rank);
+        if scores.is_empty() {
+            self.entries.remove(&key);
+        }
+        true
+    }
+
+    /// Best results of `variant`, best first
+    pub fn top(&self, variant: &Variant) -> &[Score] {
+        match self.entries.get(&variant.key()) {
+            Some(entry) => &entry.scores,
+            None => &[],
+        }
+    }
+
+    /// Highest score of `variant`, 0 if it hasn't been played
+    pub fn best(&self, variant: &Variant) -> usize {
+        self.top(variant).first().map_or(0, |score| score.score)
+    }
+
+    /// Every variant with results, in a stable order
+    pub fn variants(&self) -> impl Iterator<Item = &Variant> {
+        self.entries.values().map(|entry| &entry.variant)
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a high score table is always valid JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let value: Value = serde_json::from_str(json).map_err(LoadError::Json)?;
+        match value.get("version").and_then(Value::as_u64) {
+            Some(VERSION) => serde_json::from_value(value).map_err(LoadError::Json),
+            Some(version) => Err(LoadError::UnsupportedVersion(version)),
+            None => Err(LoadError::MissingVersion),
+        }
+    }
+}
+
+impl Default for HighScores {
+    fn default() -> Self {
+        HighScores::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(width: usize, height: usize) -> Variant {
+        Variant {
+            width,
+            height,
+            target: 2048,
+            four_chance: game::FOUR_CHANCE,
+            time_limit: None,
+        }
+    }
+
+    /// A result told apart from others with the same score by its date
+    fn score(score: usize, date: u64) -> Score {
+        Score {
+            score,
+            moves: 10,
+            max_tile: 64,
+            date,
+        }
+    }
+
+    fn points(scores: &[Score]) -> Vec<(usize, u64)> {
+        scores
+            .iter()
+            .map(|score| (score.score, score.date))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_best_results_in_order() {
+        let mut table = HighScores::with_capacity(3);
+        let square = variant(4, 4);
+        assert_eq!(table.submit(square, score(100, 0)), Some(0));
+        assert_eq!(table.submit(square, score(300, 1)), Some(0));
+        assert_eq!(table.submit(square, score(200, 2)), Some(1));
+        // Ties go to the earlier game
+        assert_eq!(table.submit(square, score(200, 3)), Some(2));
+        assert_eq!(table.submit(square, score(50, 4)), None);
+        assert_eq!(
+            points(table.top(&square)),
+            vec![(300, 1), (200, 2), (200, 3)]
+        );
+        assert_eq!(table.best(&square), 300);
+    }
+
+    #[test]
+    fn keeps_variants_apart() {
+        let mut table = HighScores::default();
+        let (square, wide) = (variant(4, 4), variant(5, 4));
+        table.submit(square, score(100, 0));
+        table.submit(wide, score(500, 1));
+        assert_eq!(points(table.top(&square)), vec![(100, 0)]);
+        assert_eq!(points(table.top(&wide)), vec![(500, 1)]);
+        assert_eq!(table.best(&variant(3, 3)), 0);
+        assert_eq!(table.variants().count(), 2);
+    }
+
+    #[test]
+    fn skips_results_without_points() {
+        let mut table = HighScores::default();
+        assert_eq!(table.submit(variant(4, 4), score(0, 0)), None);
+        assert_eq!(table.variants().count(), 0);
+        let mut table = HighScores::with_capacity(0);
+        assert_eq!(table.submit(variant(4, 4), score(100, 0)), None);
+    }
+
+    #[test]
+    fn withdraws_submitted_results() {
+        let mut table = HighScores::default();
+        let square = variant(4, 4);
+        table.submit(square, score(100, 0));
+        table.submit(square, score(100, 1));
+        assert!(table.withdraw(&square, &score(100, 1)));
+        assert_eq!(points(table.top(&square)), vec![(100, 0)]);
+        assert!(!table.withdraw(&square, &score(100, 1)));
+        assert!(!table.withdraw(&variant(5, 5), &score(100, 0)));
+        assert!(table.withdraw(&square, &score(100, 0)));
+        assert_eq!(table.variants().count(), 0);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut table = HighScores::default();
+        table.submit(variant(4, 4), score(100, 0));
+        assert_eq!(HighScores::from_json(&table.to_json()).unwrap(), table);
+        assert!(matches!(
+            HighScores::from_json(r#"{"version":2}"#),
+            Err(LoadError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+//! Statistics over every game played.
+//!
+//! Frontends add a `GameRecord` whenever a game ends, the same way they
+//! submit it to the high score table, and keep the `Stats` as JSON.
+//! `Stats::summary` boils the records down to what a dashboard shows.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::game::Game;
+use crate::save::LoadError;
+use crate::scores::Variant;
+
+/// Current version of the statistics format
+pub const VERSION: u64 = 1;
+
+/// A game that ended
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub variant: Variant,
+    pub score: usize,
+    pub max_tile: usize,
+    pub moves: usize,
+    /// Time spent playing in seconds
+    pub duration: u64,
+    pub undos: usize,
+    /// Whether the target tile was reached
+    pub won: bool,
+    /// When the game ended, in seconds since the Unix epoch
+    pub date: u64,
+}
+
+impl GameRecord {
+    /// The record of `game`, played for `duration` seconds and ending at
+    /// `date`, `None` if the game has no result yet
+    pub fn of(game: &Game, duration: u64, date: u64) -> Option<Self> {
+        if !game.has_result() {
+            return None;
+        }
+        Some(GameRecord {
+            variant: Variant::of(game),
+            score: game.get_score(),
+            max_tile: game.get_max_tile(),
+            moves: game.get_moves(),
+            duration,
+            undos: game.get_undos_used(),
+            won: game.is_won(),
+            date,
+        })
+    }
+}
+
+/// What the games of some variant, or all games, add up to
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub games: usize,
+    pub average_score: f64,
+    pub median_score: f64,
+    pub best_score: usize,
+    /// Time spent playing in seconds
+    pub total_duration: u64,
+    /// Each tile from 2 up to the largest one reached, with the number of
+    /// games reaching it
+    pub reached: Vec<(usize, usize)>,
+    /// Number of games scoring from a power of two up to the next, for
+    /// each power of two from the lowest score's to the highest's.
+    /// Scores below 2 count towards the first bucket.
+    pub histogram: Vec<(usize, usize)>,
+    /// Games won in a row up to the last one
+    pub current_streak: usize,
+    /// Most games won in a row
+    pub longest_streak: usize,
+}
+
+/// Every game played, oldest first
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    version: u64,
+    games: Vec<GameRecord>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            version: VERSION,
+            games: Vec::new(),
+        }
+    }
+
+    /// Add a game that ended, games without a single move are left out
+    pub fn record(&mut self, record: GameRecord) {
+        if record.moves > 0 {
+            self.games.push(record);
+        }
+    }
+
+    /// Take back the latest game equal to `record`, e.g. of a game that was
+    /// undone to carry on, returns whether there was one
+    pub fn withdraw(&mut self, record: &GameRecord) -> bool {
+        match self.games.iter().rposition(|game| game == record) {
+            Some(index) => {
+                self.games.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_games(&self) -> &[GameRecord] {
+        &self.games
+    }
+
+    /// Sum up the games of `variant`, or every game for `None`
+    pub fn summary(&self, variant: Option<&Variant>) -> Summary {
+        let games: Vec<&GameRecord> = self
+            .games
+            .iter()
+            .filter(|game| variant.is_none_or(|variant| game.variant == *variant))
+            .collect();
+        if games.is_empty() {
+            return Summary::default();
+        }
+
+        let mut scores: Vec<usize> = games.iter().map(|game| game.score).collect();
+        scores.sort_unstable();
+        let middle = scores.len() / 2;
+        let median_score = match scores.len() % 2 {
+            0 => (scores[middle - 1] + scores[middle]) as f64 / 2.0,
+            _ => scores[middle] as f64,
+        };
+
+        let largest = games.iter().map(|game| game.max_tile).max().unwrap_or(0);
+        let reached = std::iter::successors(Some(2), |tile: &usize| tile.checked_mul(2))
+            .take_while(|&tile| tile <= largest)
+            .map(|tile| {
+                let count = games.iter().filter(|game| game.max_tile >= tile).count();
+                (tile, count)
+            })
+            .collect();
+
+        let bucket = |score: usize| score.max(1).ilog2();
+        let (lowest, highest) = (bucket(scores[0]), bucket(scores[scores.len() - 1]));
+        let histogram = (lowest..=highest)
+            .map(|power| {
+                let count = scores
+                    .iter()
+                    .filter(|&&score| bucket(score) == power)
+                    .count();
+                (1 << power, count)
+            })
+            .collect();
+
+        let mut streak = 0;
+        let mut longest_streak = 0;
+        for game in &games {
+            streak = if game.won { streak + 1 } else { 0 };
+            longest_streak = longest_streak.max(streak);
+        }
+
+        Summary {
+            games: games.len(),
+            average_score: scores.iter().sum::<usize>() as f64 / scores.len() as f64,
+            median_score,
+            best_score: scores[scores.len() - 1],
+            total_duration: games.iter().map(|game| game.duration).sum(),
+            reached,
+            histogram,
+            current_streak: streak,
+            longest_streak,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("statistics are always valid JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let value: Value = serde_json::from_str(json).map_err(LoadError::Json)?;
+        match value.get("version").and_then(Value::as_u64) {
+            Some(VERSION) => serde_json::from_value(value).map_err(LoadError::Json),
+            Some(version) => Err(LoadError::UnsupportedVersion(version)),
+            None => Err(LoadError::MissingVersion),
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+/// Format a duration in seconds like 1:05 or 1:02:03
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match hours {
+        0 => format!("{}:{:02}", minutes, seconds),
+        _ => format!("{}:{:02}:{:02}", hours, minutes, seconds),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Direction;
+
+    fn record(score: usize, max_tile: usize, won: bool) -> GameRecord {
+        GameRecord {
+            variant: Variant::of(&Game::new()),
+            score,
+            max_tile,
+            moves: 10,
+            duration: 60,
+            undos: 0,
+            won,
+            date: 0,
+        }
+    }
+
+    fn stats(records: Vec<GameRecord>) -> Stats {
+        let mut stats = Stats::new();
+        for record in records {
+            stats.record(record);
+        }
+        stats
+    }
+
+    #[test]
+    fn counts_only_games_with_a_result() {
+        let mut game = Game::from_shape(4, 4, 1);
+        game.seed_cell();
+        game.seed_cell();
+        game.play(&Direction::Left);
+        game.play(&Direction::Up);
+        assert_eq!(GameRecord::of(&game, 5, 0), None);
+
+        // A 2x2 board fills up after a few moves
+        let mut game = Game::from_shape(2, 2, 1);
+        game.seed_cell();
+        while !game.is_finished() {
+            for direction in Direction::ALL.iter() {
+                game.play(direction);
+            }
+        }
+        let mut stats = Stats::new();
+        stats.record(GameRecord::of(&game, 5, 0).unwrap());
+        assert_eq!(stats.summary(None).games, 1);
+        assert_eq!(stats.summary(None).best_score, game.get_score());
+    }
+
+    #[test]
+    fn sums_up_scores_and_tiles() {
+        let summary = stats(vec![
+            record(100, 16, false),
+            record(700, 64, false),
+            record(300, 32, false),
+            record(1000, 128, false),
+        ])
+        .summary(None);
+        assert_eq!(summary.games, 4);
+        assert_eq!(summary.average_score, 525.0);
+        assert_eq!(summary.median_score, 500.0);
+        assert_eq!(summary.best_score, 1000);
+        assert_eq!(summary.total_duration, 240);
+        assert_eq!(
+            summary.reached,
+            vec![(2, 4), (4, 4), (8, 4), (16, 4), (32, 3), (64, 2), (128, 1)]
+        );
+        // 100 is in 64..128, 300 in 256..512, 700 and 1000 in 512..1024
+        assert_eq!(
+            summary.histogram,
+            vec![(64, 1), (128, 0), (256, 1), (512, 2)]
+        );
+
+        let odd = stats(vec![
+            record(5, 4, false),
+            record(1, 2, false),
+            record(9, 8, false),
+        ]);
+        assert_eq!(odd.summary(None).median_score, 5.0);
+        assert_eq!(odd.summary(None).histogram[0], (1, 1));
+    }
+
+    #[test]
+    fn counts_winning_streaks() {
+        let won = |won| record(100, 16, won);
+        let summary = stats(vec![
+            won(true),
+            won(true),
+            won(true),
+            won(false),
+            won(true),
+            won(true),
+        ])
+        .summary(None);
+        assert_eq!((summary.current_streak, summary.longest_streak), (2, 3));
+        let summary = stats(vec![won(true), won(false)]).summary(None);
+        assert_eq!((summary.current_streak, summary.longest_streak), (0, 1));
+    }
+
+    #[test]
+    fn keeps_variants_apart() {
+        let mut wide = record(500, 64, false);
+        wide.variant.width = 5;
+        let stats = stats(vec![record(100, 16, false), wide.clone()]);
+        assert_eq!(stats.summary(Some(&wide.variant)).best_score, 500);
+        assert_eq!(stats.summary(None).games, 2);
+        let mut other = wide.variant;
+        other.height = 5;
+        assert_eq!(stats.summary(Some(&other)), Summary::default());
+    }
+
+    #[test]
+    fn withdraws_the_latest_matching_game() {
+        let mut first = record(100, 16, false);
+        first.date = 1;
+        let mut stats = stats(vec![first.clone(), record(100, 16, false), first.clone()]);
+        assert!(stats.withdraw(&first));
+        assert_eq!(stats.get_games(), &[first.clone(), record(100, 16, false)]);
+        assert!(stats.withdraw(&first));
+        assert!(!stats.withdraw(&first));
+        assert_eq!(stats.get_games().len(), 1);
+    }
+}
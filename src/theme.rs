@@ -0,0 +1,258 @@
+//! Colours and fonts the frontends draw the game with.
+//!
+//! The rules don't care what a tile looks like, so a `Theme` belongs to
+//! whatever renders the board. Besides the built-in themes, custom ones
+//! can be loaded from JSON such as
+//!
+//! ```json
+//! { "name": "midnight", "page": "#101020", "board": "#303050" }
+//! ```
+//!
+//! where anything left out is taken from the classic theme and a missing
+//! name becomes "custom".
+
+use serde::{Deserialize, Serialize};
+
+/// Names of the built-in themes, the first one is the default
+pub const BUILTIN: [&str; 3] = ["classic", "dark", "high-contrast"];
+
+/// How a tile of some value is drawn
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileStyle {
+    pub background: String,
+    /// Colour of the number
+    pub foreground: String,
+}
+
+impl TileStyle {
+    fn new(background: &str, foreground: &str) -> Self {
+        TileStyle {
+            background: background.to_owned(),
+            foreground: foreground.to_owned(),
+        }
+    }
+}
+
+/// Everything about how the game looks
+///
+/// Colours are written like "#eee4da" so every frontend can use them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(default = "custom_name")]
+    pub name: String,
+    /// Font family of the numbers and messages on the board
+    pub font: String,
+    /// Around the board
+    pub page: String,
+    /// Text on the page and messages on the board
+    pub text: String,
+    pub board: String,
+    /// Text drawn straight on the board's colour, like the score labels
+    pub board_text: String,
+    /// Laid over the board behind messages
+    pub overlay: String,
+    pub empty: TileStyle,
+    /// Tiles 2, 4, 8 and so on, in order
+    pub tiles: Vec<TileStyle>,
+    /// Tiles too large to be in `tiles`
+    pub super_tile: TileStyle,
+}
+
+impl Theme {
+    /// The colours of the original game
+    pub fn classic() -> Self {
+        let dark = "#898077";
+        let light = "#f9f6f2";
+        Theme {
+            name: "classic".to_owned(),
+            font: "Sans-Serif".to_owned(),
+            page: "#f9f6f2".to_owned(),
+            text: "#776e65".to_owned(),
+            board: "#bbada1".to_owned(),
+            board_text: "#f9f6f2".to_owned(),
+            overlay: "#eee4da".to_owned(),
+            empty: TileStyle::new("#cdc1b4", dark),
+            tiles: vec![
+                TileStyle::new("#eee4da", dark),
+                TileStyle::new("#ede0c8", dark),
+                TileStyle::new("#f2b179", light),
+                TileStyle::new("#f59563", light),
+                TileStyle::new("#f67c5f", light),
+                TileStyle::new("#f65e3b", light),
+                TileStyle::new("#edcf72", light),
+                TileStyle::new("#edcc61", light),
+                TileStyle::new("#edc850", light),
+                TileStyle::new("#edc53f", light),
+                TileStyle::new("#edc22e", light),
+                TileStyle::new("#fb736d", light),
+                TileStyle::new("#fb5c5a", light),
+                TileStyle::new("#f0513b", light),
+                TileStyle::new("#6baed5", light),
+                TileStyle::new("#569be0", light),
+                TileStyle::new("#1883cc", light),
+            ],
+            super_tile: TileStyle::new("#3c3a32", light),
+        }
+    }
+
+    /// Muted tiles on a dark board
+    pub fn dark() -> Self {
+        let light = "#f4efe9";
+        Theme {
+            name: "dark".to_owned(),
+            font: "Sans-Serif".to_owned(),
+            page: "#1b1b1d".to_owned(),
+            text: "#e8e2da".to_owned(),
+            board: "#3b3835".to_owned(),
+            board_text: light.to_owned(),
+            overlay: "#1b1b1d".to_owned(),
+            empty: TileStyle::new("#4b4743", "#4b4743"),
+            tiles: vec![
+                TileStyle::new("#5c564f", light),
+                TileStyle::new("#6b6152", light),
+                TileStyle::new("#a5673a", light),
+                TileStyle::new("#b5552f", light),
+                TileStyle::new("#b94432", light),
+                TileStyle::new("#b8321d", light),
+                TileStyle::new("#a88d2e", light),
+                TileStyle::new("#ad8c22", light),
+                TileStyle::new("#b08a16", light),
+                TileStyle::new("#b6870d", light),
+                TileStyle::new("#bb8504", light),
+                TileStyle::new("#9b3c4a", light),
+                TileStyle::new("#8a2a3c", light),
+                TileStyle::new("#742036", light),
+                TileStyle::new("#2f6d8f", light),
+                TileStyle::new("#255d96", light),
+                TileStyle::new("#134f85", light),
+            ],
+            super_tile: TileStyle::new("#0f0f10", light),
+        }
+    }
+
+    /// Strong contrast between neighbouring tiles and their numbers, with
+    /// colours that stay apart under the common kinds of colour blindness
+    pub fn high_contrast() -> Self {
+        let black = "#000000";
+        let white = "#ffffff";
+        Theme {
+            name: "high-contrast".to_owned(),
+            font: "Verdana, Sans-Serif".to_owned(),
+            page: black.to_owned(),
+            text: white.to_owned(),
+            board: "#1a1a1a".to_owned(),
+            board_text: white.to_owned(),
+            overlay: black.to_owned(),
+            empty: TileStyle::new("#333333", "#333333"),
+            // Okabe and Ito's palette, then a few of Paul Tol's
+            tiles: vec![
+                TileStyle::new(white, black),
+                TileStyle::new("#f0e442", black),
+                TileStyle::new("#e69f00", black),
+                TileStyle::new("#56b4e9", black),
+                TileStyle::new("#009e73", white),
+                TileStyle::new("#0072b2", white),
+                TileStyle::new("#d55e00", white),
+                TileStyle::new("#cc79a7", black),
+                TileStyle::new("#999999", black),
+                TileStyle::new("#882255", white),
+                TileStyle::new("#44aa99", black),
+            ],
+            super_tile: TileStyle::new("#332288", white),
+        }
+    }
+
+    /// All built-in themes, in the order of `BUILTIN`
+    pub fn builtin() -> Vec<Theme> {
+        vec![Theme::classic(), Theme::dark(), Theme::high_contrast()]
+    }
+
+    /// The built-in theme called `name`
+    pub fn named(name: &str) -> Option<Theme> {
+        Theme::builtin()
+            .into_iter()
+            .find(|theme| theme.name == name)
+    }
+
+    /// How to draw a tile of `value`, 0 being an empty cell
+    pub fn tile(&self, value: usize) -> &TileStyle {
+        if value == 0 {
+            return &self.empty;
+        }
+        let index = value.trailing_zeros() as usize;
+        self.tiles
+            .get(index.saturating_sub(1))
+            .unwrap_or(&self.super_tile)
+    }
+
+    /// Every colour of the theme
+    fn colors(&self) -> impl Iterator<Item = &String> {
+        self.tiles
+            .iter()
+            .chain([&self.empty, &self.super_tile])
+            .flat_map(|style| [&style.background, &style.foreground])
+            .chain([
+                &self.page,
+                &self.text,
+                &self.board,
+                &self.board_text,
+                &self.overlay,
+            ])
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a theme is always valid JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ThemeError> {
+        let theme: Theme = serde_json::from_str(json).map_err(ThemeError::Json)?;
+        let invalid = theme.colors().find(|color| !is_hex_color(color)).cloned();
+        match invalid {
+            Some(color) => Err(ThemeError::InvalidColor(color)),
+            None => Ok(theme),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    /// The theme isn't valid JSON or doesn't match the format
+    Json(serde_json::Error),
+    /// A colour isn't written like "#eee4da"
+    InvalidColor(String),
+}
+
+impl std::fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ThemeError::Json(error) => write!(f, "malformed theme: {}", error),
+            ThemeError::InvalidColor(color) => write!(f, "invalid colour {:?}", color),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// Add `theme` to `themes`, replacing any theme of the same name
+pub fn add_theme(themes: &mut Vec<Theme>, theme: Theme) {
+    match themes.iter_mut().find(|other| other.name == theme.name) {
+        Some(other) => *other = theme,
+        None => themes.push(theme),
+    }
+}
+
+fn custom_name() -> String {
+    "custom".to_owned()
+}
+
+/// Whether `color` is written like "#eee4da"
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -0,0 +1,531 @@
+use stdweb::traits::*;
+use stdweb::unstable::TryInto;
+use stdweb::web::html_element::CanvasElement;
+use stdweb::web::{document, window, CanvasRenderingContext2d, FillRule, TextBaseline};
+
+use rs2048::outcome::MoveOutcome;
+use rs2048::stats::format_duration;
+use rs2048::theme::Theme;
+use rs2048::{Direction, Game};
+
+use crate::web::util::Point;
+
+// Sizes in CSS pixels for a cell of `CELL_SIZE` or a board of
+// `BOARD_SIZE`, they are scaled along with the board
+const BORDER_X: f64 = 10.0;
+const BORDER_Y: f64 = 10.0;
+const OFFSET_Y: f64 = 20.0;
+const FONT_SIZE: f64 = 55.0;
+/// Largest length in CSS pixels of the longer side of the board
+const BOARD_SIZE: f64 = 600.0;
+/// Cell size of a 4x4 board at full size
+const CELL_SIZE: f64 = 150.0;
+/// Widest a number may be in multiples of its font size per digit, to
+/// shrink the font for long numbers
+const DIGIT_WIDTH: f64 = 0.6;
+/// Space in CSS pixels kept free around the board when it has to fit the
+/// window's height
+const WINDOW_MARGIN: f64 = 16.0;
+/// Opacity of the theme's overlay colour behind messages
+const OVERLAY_ALPHA: f64 = 0.73;
+/// Opacity of the theme's text colour for hint arrows
+const HINT_ALPHA: f64 = 0.6;
+/// Size in CSS pixels of the overlays' buttons on a board at full size
+const BUTTON_WIDTH: f64 = 160.0;
+const BUTTON_HEIGHT: f64 = 50.0;
+const BUTTON_GAP: f64 = 20.0;
+/// Fraction of an animation spent sliding, the rest is popping and growing
+const SLIDE_PHASE: f64 = 0.6;
+/// How much bigger a merged tile gets at the height of its pop
+const POP_SCALE: f64 = 0.2;
+
+/// What the game-over overlay reports about a finished game
+pub struct Summary {
+    pub score: usize,
+    pub max_tile: usize,
+    pub moves: usize,
+    /// Time spent playing in seconds
+    pub duration: u64,
+    /// Whether the score beats the variant's record
+    pub new_best: bool,
+    /// Whether the last move can be taken back
+    pub can_undo: bool,
+}
+
+/// A button on the game-over or victory overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayAction {
+    NewGame,
+    Undo,
+    KeepGoing,
+}
+
+/// A move being animated, from the board before it to `game`'s board
+struct Animation {
+    outcome: MoveOutcome,
+    start: Option<f64>,
+}
+
+pub struct Canvas {
+    pub canvas: CanvasElement,
+    pub ctx: CanvasRenderingContext2d,
+    animation: Option<Animation>,
+    duration: f64,
+    /// Board size in cells
+    shape: (usize, usize),
+    /// Canvas size in CSS pixels, everything is drawn in these
+    width: f64,
+    height: f64,
+    /// Device pixels per CSS pixel the canvas was last sized for
+    pixel_ratio: f64,
+    theme: Theme,
+    /// Buttons on the overlay as drawn, with their left, top, width and
+    /// height in CSS pixels
+    buttons: Vec<(OverlayAction, [f64; 4])>,
+}
+
+impl Canvas {
+    pub fn new(attr_id: &str) -> Canvas {
+        let canvas: CanvasElement = document()
+            .query_selector(attr_id)
+            .unwrap()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let ctx: CanvasRenderingContext2d = canvas.get_context().unwrap();
+
+        let mut canvas = Canvas {
+            canvas,
+            ctx,
+            animation: None,
+            duration: 0.0,
+            shape: (4, 4),
+            width: 0.0,
+            height: 0.0,
+            pixel_ratio: 0.0,
+            theme: Theme::default(),
+            buttons: Vec::new(),
+        };
+        canvas.resize();
+        canvas
+    }
+
+    /// Fit a board `width` cells across and `height` cells down with
+    /// square cells
+    pub fn set_shape(&mut self, width: usize, height: usize) {
+        if self.shape != (width, height) {
+            self.shape = (width, height);
+            self.resize();
+        }
+    }
+
+    /// Size the canvas to fill its container without overflowing the
+    /// window, at the display's pixel density
+    ///
+    /// Resizing clears the canvas, so nothing happens if it already fits.
+    /// Returns whether the board needs to be redrawn.
+    pub fn resize(&mut self) -> bool {
+        let (columns, rows) = (self.shape.0.max(1) as f64, self.shape.1.max(1) as f64);
+        let available_width = self
+            .canvas
+            .parent_node()
+            .and_then(|parent| js!( return @{parent}.clientWidth; ).try_into().ok())
+            .filter(|&width: &f64| width > 0.0)
+            .unwrap_or(BOARD_SIZE);
+        let available_height = f64::from(window().inner_height()) - 2.0 * WINDOW_MARGIN;
+        let cell = (available_width / columns)
+            .min(available_height / rows)
+            .min(BOARD_SIZE / columns.max(rows))
+            .max(1.0)
+            .floor();
+        let pixel_ratio = window().device_pixel_ratio().max(1.0);
+        let (width, height) = (cell * columns, cell * rows);
+        if (width, height, pixel_ratio) == (self.width, self.height, self.pixel_ratio) {
+            return false;
+        }
+
+        self.width = width;
+        self.height = height;
+        self.pixel_ratio = pixel_ratio;
+        // Draw in CSS pixels on a backing store in device pixels
+        self.canvas.set_width((width * pixel_ratio).round() as u32);
+        self.canvas
+            .set_height((height * pixel_ratio).round() as u32);
+        js! { @(no_return)
+            var canvas = @{&self.canvas};
+            canvas.style.width = @{width} + "px";
+            canvas.style.height = @{height} + "px";
+            @{&self.ctx}.setTransform(@{pixel_ratio}, 0, 0, @{pixel_ratio}, 0, 0);
+        }
+        true
+    }
+
+    /// Draw everything in `theme` from now on, the board needs to be
+    /// redrawn to show it
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// How much smaller the board is than at full size
+    fn scale(&self) -> f64 {
+        self.width.max(self.height) / BOARD_SIZE
+    }
+
+    /// Draw a tile at a (possibly fractional) cell position on a board of
+    /// `shape` (width, height) cells, `scale` shrinks or grows it around
+    /// its center
+    pub fn draw_tile(&self, x: f64, y: f64, shape: (usize, usize), value: usize, scale: f64) {
+        let scaled_width: f64 = self.width / shape.0 as f64;
+        let scaled_height: f64 = self.height / shape.1 as f64;
+        let cell_scale = scaled_width.min(scaled_height) / CELL_SIZE;
+        let center_x = (x + 0.5) * scaled_width;
+        let center_y = (y + 0.5) * scaled_height;
+        let tile_width = (scaled_width - 2.0 * BORDER_X * cell_scale) * scale;
+        let tile_height = (scaled_height - 2.0 * BORDER_Y * cell_scale) * scale;
+
+        // draw rectangle
+        let style = self.theme.tile(value);
+        self.ctx.set_fill_style_color(&style.background);
+        self.ctx.fill_rect(
+            center_x - 0.5 * tile_width,
+            center_y - 0.5 * tile_height,
+            tile_width,
+            tile_height,
+        );
+
+        // insert text, shrinking the font for long numbers
+        let text = value.to_string();
+        let font_size = (FONT_SIZE * cell_scale * scale)
+            .min(0.85 * tile_width / (DIGIT_WIDTH * text.len() as f64));
+        self.ctx.set_fill_style_color(&style.foreground);
+        self.ctx
+            .set_font(&format!("{}px {}", font_size, self.theme.font));
+        self.ctx.set_text_align(stdweb::web::TextAlign::Center);
+        self.ctx.set_text_baseline(TextBaseline::Middle);
+        self.ctx
+            .fill_text(&text, center_x, center_y, Some(0.9 * tile_width));
+    }
+
+    // pub fn draw_rect(&self, x: usize, y: usize, size: usize, color: &str) {
+    //     let scaled_width: f64 = f64::from(self.canvas.width()) / f64::from(size as u32);
+    //     let scaled_height: f64 = f64::from(self.canvas.height()) / f64::from(size as u32);
+    //     self.ctx.set_fill_style_color(color);
+    //     self.ctx.fill_rect(
+    //         f64::from(x as u32) * scaled_width,
+    //         f64::from(y as u32) * scaled_height,
+    //         scaled_width,
+    //         scaled_height,
+    //     );
+    // }
+
+    pub fn draw_board(&mut self, game: &Game) {
+        self.buttons.clear();
+        self.clear_all();
+        let shape = (game.get_width(), game.get_height());
+        for y in 0..shape.1 {
+            for x in 0..shape.0 {
+                let value = game.get_state(x, y);
+                if value != 0 {
+                    self.draw_tile(x as f64, y as f64, shape, value, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Set how long a move is animated in milliseconds, 0 disables animations
+    pub fn set_animation_duration(&mut self, duration: f64) {
+        self.duration = duration;
+    }
+
+    /// Animate the move described by `outcome` over the next frames
+    pub fn start_animation(&mut self, outcome: MoveOutcome) {
+        self.animation = Some(Animation {
+            outcome,
+            start: None,
+        });
+    }
+
+    pub fn stop_animation(&mut self) {
+        self.animation = None;
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Draw the current frame of the running animation at `timestamp`
+    /// (as passed to a `requestAnimationFrame` callback)
+    ///
+    /// Returns true on the frame the animation finishes, at which point
+    /// the final board has been drawn.
+    pub fn draw_frame(&mut self, game: &Game, timestamp: f64) -> bool {
+        if self.animation.is_some() {
+            self.buttons.clear();
+        }
+        let progress = match self.animation.as_mut() {
+            Some(animation) => {
+                let start = *animation.start.get_or_insert(timestamp);
+                if self.duration > 0.0 {
+                    (timestamp - start) / self.duration
+                } else {
+                    1.0
+                }
+            }
+            None => return false,
+        };
+        if progress >= 1.0 {
+            self.animation = None;
+            self.draw_board(game);
+            return true;
+        }
+        if let Some(animation) = &self.animation {
+            self.draw_animation(game, &animation.outcome, progress);
+        }
+        false
+    }
+
+    /// Draw the game-over overlay reporting on the game, with buttons
+    /// to start a new one and to take back the last move
+    pub fn draw_game_over(&mut self, summary: &Summary) {
+        let (width, height) = (self.width, self.height);
+        let scale = self.scale();
+        let center_y = 0.5 * height;
+        let font = &self.theme.font;
+
+        self.draw_veil();
+        self.ctx.set_fill_style_color(&self.theme.text);
+        self.ctx.set_text_align(stdweb::web::TextAlign::Center);
+        self.ctx.set_text_baseline(TextBaseline::Alphabetic);
+        self.ctx
+            .set_font(&format!("bold {}px {}", 60.0 * scale, font));
+        self.ctx.fill_text(
+            "Game over!",
+            0.5 * width,
+            center_y - 5.5 * OFFSET_Y * scale,
+            Some(width),
+        );
+        if summary.new_best {
+            self.ctx
+                .set_font(&format!("bold {}px {}", 28.0 * scale, font));
+            self.ctx.fill_text(
+                "New best!",
+                0.5 * width,
+                center_y - 3.0 * OFFSET_Y * scale,
+                Some(width),
+            );
+        }
+        self.ctx.set_font(&format!("{}px {}", 22.0 * scale, font));
+        self.ctx.fill_text(
+            &format!("Score {}, highest tile {}", summary.score, summary.max_tile),
+            0.5 * width,
+            center_y - 0.5 * OFFSET_Y * scale,
+            Some(width),
+        );
+        self.ctx.fill_text(
+            &format!(
+                "{} moves in {}",
+                summary.moves,
+                format_duration(summary.duration)
+            ),
+            0.5 * width,
+            center_y + 1.25 * OFFSET_Y * scale,
+            Some(width),
+        );
+
+        let mut actions = vec![(OverlayAction::NewGame, "New game")];
+        if summary.can_undo {
+            actions.push((OverlayAction::Undo, "Undo"));
+        }
+        self.draw_buttons(&actions, center_y + 2.5 * OFFSET_Y * scale);
+    }
+
+    /// Draw a centered row of buttons for `actions` starting `top` pixels
+    /// down the board, replacing any buttons drawn before
+    fn draw_buttons(&mut self, actions: &[(OverlayAction, &str)], top: f64) {
+        let width = self.width;
+        let scale = self.scale();
+        let (button_width, button_height, gap) = (
+            BUTTON_WIDTH * scale,
+            BUTTON_HEIGHT * scale,
+            BUTTON_GAP * scale,
+        );
+        let row_width = actions.len() as f64 * (button_width + gap) - gap;
+        self.buttons.clear();
+        self.ctx.set_text_baseline(TextBaseline::Middle);
+        self.ctx
+            .set_font(&format!("bold {}px {}", 22.0 * scale, self.theme.font));
+        for (i, &(action, label)) in actions.iter().enumerate() {
+            let left = 0.5 * (width - row_width) + i as f64 * (button_width + gap);
+            self.ctx.set_fill_style_color(&self.theme.board);
+            self.ctx.fill_rect(left, top, button_width, button_height);
+            self.ctx.set_fill_style_color(&self.theme.board_text);
+            self.ctx.fill_text(
+                label,
+                left + 0.5 * button_width,
+                top + 0.5 * button_height,
+                Some(button_width),
+            );
+            self.buttons
+                .push((action, [left, top, button_width, button_height]));
+        }
+    }
+
+    /// The overlay button at `point` (in client coordinates), if any
+    pub fn action_at(&self, point: &Point) -> Option<OverlayAction> {
+        let rect = self.canvas.get_bounding_client_rect();
+        let x = point.get_x() - rect.get_left();
+        let y = point.get_y() - rect.get_top();
+        self.buttons
+            .iter()
+            .find(|(_, [left, top, width, height])| {
+                x >= *left && x <= left + width && y >= *top && y <= top + height
+            })
+            .map(|&(action, _)| action)
+    }
+
+    /// Draw the victory overlay, with a button to keep playing
+    pub fn draw_victory(&mut self) {
+        self.draw_overlay("You win!", "Press C to keep going");
+        let top = 0.5 * self.height + 4.5 * OFFSET_Y * self.scale();
+        self.draw_buttons(&[(OverlayAction::KeepGoing, "Keep going")], top);
+    }
+
+    /// Draw a large arrow in the middle of the board pointing in `direction`
+    pub fn draw_hint(&self, direction: Direction) {
+        let (width, height) = (self.width, self.height);
+        let length = 0.4 * width.min(height);
+        let angle = match direction {
+            Direction::Right => 0.0,
+            Direction::Down => 0.5 * std::f64::consts::PI,
+            Direction::Left => std::f64::consts::PI,
+            Direction::Up => -0.5 * std::f64::consts::PI,
+        };
+
+        // draw an arrow pointing right and rotate it into place
+        self.ctx.save();
+        self.ctx.translate(0.5 * width, 0.5 * height);
+        self.ctx.rotate(angle);
+        self.ctx.set_fill_style_color(&self.theme.text);
+        self.ctx.set_global_alpha(HINT_ALPHA);
+        self.ctx.begin_path();
+        self.ctx.move_to(-0.5 * length, -0.1 * length);
+        self.ctx.line_to(0.1 * length, -0.1 * length);
+        self.ctx.line_to(0.1 * length, -0.3 * length);
+        self.ctx.line_to(0.5 * length, 0.0);
+        self.ctx.line_to(0.1 * length, 0.3 * length);
+        self.ctx.line_to(0.1 * length, 0.1 * length);
+        self.ctx.line_to(-0.5 * length, 0.1 * length);
+        self.ctx.close_path();
+        self.ctx.fill(FillRule::NonZero);
+        self.ctx.restore();
+    }
+
+    /// Draw a translucent overlay with a centered message and a smaller
+    /// hint below it on top of the board
+    pub fn draw_overlay(&self, message: &str, hint: &str) {
+        let (width, height) = (self.width, self.height);
+
+        self.draw_veil();
+
+        let scale = self.scale();
+        self.ctx.set_fill_style_color(&self.theme.text);
+        self.ctx
+            .set_font(&format!("bold {}px {}", 60.0 * scale, self.theme.font));
+        self.ctx.set_text_align(stdweb::web::TextAlign::Center);
+        self.ctx.set_text_baseline(TextBaseline::Alphabetic);
+        self.ctx
+            .fill_text(message, 0.5 * width, 0.5 * height, Some(width));
+
+        self.ctx
+            .set_font(&format!("{}px {}", 25.0 * scale, self.theme.font));
+        self.ctx.fill_text(
+            hint,
+            0.5 * width,
+            0.5 * height + 3.0 * OFFSET_Y * scale,
+            Some(width),
+        );
+    }
+
+    /// Lay the theme's translucent overlay colour over the whole board
+    fn draw_veil(&self) {
+        self.ctx.save();
+        self.ctx.set_fill_style_color(&self.theme.overlay);
+        self.ctx.set_global_alpha(OVERLAY_ALPHA);
+        self.ctx.fill_rect(0.0, 0.0, self.width, self.height);
+        self.ctx.restore();
+    }
+
+    /// Draw a line of small text along the bottom of the board
+    pub fn draw_caption(&self, text: &str) {
+        let (width, height) = (self.width, self.height);
+
+        let scale = self.scale();
+        self.ctx.set_fill_style_color(&self.theme.text);
+        self.ctx
+            .set_font(&format!("{}px {}", 20.0 * scale, self.theme.font));
+        self.ctx.set_text_align(stdweb::web::TextAlign::Center);
+        self.ctx.set_text_baseline(TextBaseline::Alphabetic);
+        self.ctx
+            .fill_text(text, 0.5 * width, height - BORDER_Y * scale, Some(width));
+    }
+
+    /// Draw a move `progress` (from 0 to 1) of the way through
+    ///
+    /// Tiles slide first, then merged tiles pop and the new tile grows in.
+    fn draw_animation(&self, game: &Game, outcome: &MoveOutcome, progress: f64) {
+        let shape = (game.get_width(), game.get_height());
+        let slide = (progress / SLIDE_PHASE).min(1.0);
+        let grow = ((progress - SLIDE_PHASE) / (1.0 - SLIDE_PHASE)).max(0.0);
+        let lerp = |from: (usize, usize), to: (usize, usize)| {
+            (
+                from.0 as f64 + (to.0 as f64 - from.0 as f64) * slide,
+                from.1 as f64 + (to.1 as f64 - from.1 as f64) * slide,
+            )
+        };
+
+        self.clear_all();
+
+        // tiles that didn't take part in the move stay where they are
+        for y in 0..shape.1 {
+            for x in 0..shape.0 {
+                let value = game.get_state(x, y);
+                let moving = outcome.slides.iter().any(|slide| slide.to == (x, y))
+                    || outcome.merges.iter().any(|merge| merge.target == (x, y))
+                    || outcome.spawn.map(|spawn| spawn.position) == Some((x, y));
+                if value != 0 && !moving {
+                    self.draw_tile(x as f64, y as f64, shape, value, 1.0);
+                }
+            }
+        }
+
+        for slide in &outcome.slides {
+            let (x, y) = lerp(slide.from, slide.to);
+            self.draw_tile(x, y, shape, game.get_state(slide.to.0, slide.to.1), 1.0);
+        }
+
+        for merge in &outcome.merges {
+            if grow == 0.0 {
+                for &source in &merge.sources {
+                    let (x, y) = lerp(source, merge.target);
+                    self.draw_tile(x, y, shape, merge.value / 2, 1.0);
+                }
+            } else {
+                let scale = 1.0 + POP_SCALE * (std::f64::consts::PI * grow).sin();
+                let (x, y) = merge.target;
+                self.draw_tile(x as f64, y as f64, shape, merge.value, scale);
+            }
+        }
+
+        if let Some(spawn) = outcome.spawn {
+            if grow > 0.0 {
+                let (x, y) = spawn.position;
+                self.draw_tile(x as f64, y as f64, shape, spawn.value, grow);
+            }
+        }
+    }
+
+    pub fn clear_all(&self) {
+        self.ctx.set_fill_style_color(&self.theme.board);
+        self.ctx.fill_rect(0.0, 0.0, self.width, self.height);
+    }
+}
@@ -0,0 +1,10 @@
+//! The stdweb frontend: rendering to a canvas, the scoreboard, input
+//! handling and local storage.
+
+pub mod canvas;
+pub mod replay;
+pub mod scoreboard;
+pub mod share;
+pub mod storage;
+pub mod theme;
+pub mod util;
@@ -0,0 +1,102 @@
+use rs2048::outcome::MoveOutcome;
+use rs2048::replay::{Player, Replay};
+use rs2048::save::LoadError;
+use rs2048::Game;
+
+/// Playback speeds in ticks per move, fastest last
+const SPEEDS: [u32; 4] = [8, 4, 2, 1];
+
+/// Plays a replay back in the browser, one move every few ticks
+pub struct ReplayViewer {
+    player: Player,
+    playing: bool,
+    speed: usize,
+    ticks: u32,
+}
+
+impl ReplayViewer {
+    /// Start at the beginning of `replay`, playing
+    pub fn new(replay: Replay) -> Result<Self, LoadError> {
+        Ok(ReplayViewer {
+            player: Player::new(replay)?,
+            playing: true,
+            speed: 1,
+            ticks: 0,
+        })
+    }
+
+    pub fn get_game(&self) -> &Game {
+        self.player.get_game()
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        self.player.get_replay()
+    }
+
+    pub fn toggle_playing(&mut self) {
+        // Playing from the end starts over
+        if !self.playing && self.player.is_at_end() {
+            self.player.seek(0);
+        }
+        self.playing = !self.playing;
+        self.ticks = 0;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Pause and play the next move
+    pub fn step_forward(&mut self) -> Option<MoveOutcome> {
+        self.playing = false;
+        self.player.step_forward()
+    }
+
+    /// Pause and take back the last move
+    pub fn step_back(&mut self) -> bool {
+        self.playing = false;
+        self.player.step_back()
+    }
+
+    /// Pause and jump to the position after `index` moves
+    pub fn seek(&mut self, index: usize) {
+        self.playing = false;
+        self.player.seek(index);
+    }
+
+    pub fn len(&self) -> usize {
+        self.player.len()
+    }
+
+    /// Advance playback by a tick, returns the move made if any
+    pub fn tick(&mut self) -> Option<MoveOutcome> {
+        if !self.playing {
+            return None;
+        }
+        self.ticks += 1;
+        if self.ticks < SPEEDS[self.speed] {
+            return None;
+        }
+        self.ticks = 0;
+        let outcome = self.player.step_forward();
+        if self.player.is_at_end() {
+            self.playing = false;
+        }
+        outcome
+    }
+
+    /// A line describing where playback is
+    pub fn status(&self) -> String {
+        format!(
+            "Replay {}/{} {} {}x",
+            self.player.get_index(),
+            self.player.len(),
+            if self.playing { "playing" } else { "paused" },
+            SPEEDS[0] / SPEEDS[self.speed],
+        )
+    }
+}
@@ -0,0 +1,157 @@
+use stdweb::traits::*;
+use stdweb::web::{document, Element};
+
+use rs2048::scores::{Score, Variant};
+use rs2048::stats::{format_duration, Summary};
+use rs2048::Game;
+
+pub struct Scoreboard {
+    pub scoreboard: Element,
+    pub best: Element,
+    pub records: Element,
+    pub stats: Element,
+}
+
+impl Scoreboard {
+    pub fn new(
+        attr_id_scoreboard: &str,
+        attr_id_best: &str,
+        attr_id_records: &str,
+        attr_id_stats: &str,
+    ) -> Self {
+        let scoreboard: Element = document()
+            .query_selector(attr_id_scoreboard)
+            .unwrap()
+            .unwrap();
+
+        let best: Element = document().query_selector(attr_id_best).unwrap().unwrap();
+        let records: Element = document().query_selector(attr_id_records).unwrap().unwrap();
+        let stats: Element = document().query_selector(attr_id_stats).unwrap().unwrap();
+
+        Scoreboard {
+            scoreboard,
+            best,
+            records,
+            stats,
+        }
+    }
+
+    pub fn draw_score(&self, game: &Game) {
+        self.scoreboard
+            .set_text_content(&format!("{}", game.get_score()));
+        self.best.set_text_content(&format!("{}", game.get_best()));
+    }
+
+    /// List the best results of `variant`, best first
+    pub fn draw_records(&self, variant: &Variant, scores: &[Score]) {
+        while let Some(child) = self.records.first_child() {
+            let _ = self.records.remove_child(&child);
+        }
+
+        let title = document().create_element("h3").unwrap();
+        title.set_text_content(&format!("Records {}", variant.name()));
+        self.records.append_child(&title);
+
+        let list = document().create_element("ol").unwrap();
+        for score in scores {
+            let date = js! {
+                return new Date(@{score.date as f64} * 1000).toLocaleDateString();
+            }
+            .into_string()
+            .unwrap_or_default();
+            let item = document().create_element("li").unwrap();
+            item.set_text_content(&format!(
+                "{} ({}, {} moves, {})",
+                score.score, score.max_tile, score.moves, date
+            ));
+            list.append_child(&item);
+        }
+        if scores.is_empty() {
+            let item = document().create_element("li").unwrap();
+            item.set_text_content("No records yet");
+            list.append_child(&item);
+        }
+        self.records.append_child(&list);
+    }
+
+    /// Show what all games played add up to: averages, how often each
+    /// tile was reached and a histogram of the scores
+    pub fn draw_stats(&self, summary: &Summary) {
+        while let Some(child) = self.stats.first_child() {
+            let _ = self.stats.remove_child(&child);
+        }
+        if summary.games == 0 {
+            self.stats.set_text_content("No games played yet");
+            return;
+        }
+
+        let table = document().create_element("table").unwrap();
+        let rows = [
+            ("Games played", summary.games.to_string()),
+            ("Average score", format!("{:.0}", summary.average_score)),
+            ("Median score", format!("{:.0}", summary.median_score)),
+            ("Best score", summary.best_score.to_string()),
+            ("Time played", format_duration(summary.total_duration)),
+            ("Win streak", summary.current_streak.to_string()),
+            ("Longest win streak", summary.longest_streak.to_string()),
+        ];
+        for (label, value) in rows.iter() {
+            append_row(&table, label, value, None);
+        }
+        self.stats.append_child(&table);
+
+        let title = document().create_element("h4").unwrap();
+        title.set_text_content("Tiles reached");
+        self.stats.append_child(&title);
+        // Start at the largest tile every game reached
+        let start = summary
+            .reached
+            .iter()
+            .rposition(|&(_, count)| count == summary.games)
+            .unwrap_or(0);
+        let table = document().create_element("table").unwrap();
+        for &(tile, count) in &summary.reached[start..] {
+            let share = count as f64 / summary.games as f64;
+            let value = format!("{:.1}%", 100.0 * share);
+            append_row(&table, &tile.to_string(), &value, Some(share));
+        }
+        self.stats.append_child(&table);
+
+        let title = document().create_element("h4").unwrap();
+        title.set_text_content("Scores");
+        self.stats.append_child(&title);
+        let most = summary
+            .histogram
+            .iter()
+            .map(|&(_, count)| count)
+            .max()
+            .unwrap_or(1);
+        let table = document().create_element("table").unwrap();
+        for &(from, count) in &summary.histogram {
+            let label = format!("{}\u{2013}{}", from, 2 * from - 1);
+            let share = count as f64 / most as f64;
+            append_row(&table, &label, &count.to_string(), Some(share));
+        }
+        self.stats.append_child(&table);
+    }
+}
+
+/// Add a row with a label, a value and optionally a bar `fill` (from 0 to
+/// 1) of the way across to `table`
+fn append_row(table: &Element, label: &str, value: &str, fill: Option<f64>) {
+    let row = document().create_element("tr").unwrap();
+    for text in &[label, value] {
+        let cell = document().create_element("td").unwrap();
+        cell.set_text_content(text);
+        row.append_child(&cell);
+    }
+    if let Some(fill) = fill {
+        let cell = document().create_element("td").unwrap();
+        let bar = document().create_element("div").unwrap();
+        let _ = bar.set_attribute("class", "stats-bar");
+        let _ = bar.set_attribute("style", &format!("width: {:.0}%", 100.0 * fill));
+        cell.append_child(&bar);
+        row.append_child(&cell);
+    }
+    table.append_child(&row);
+}
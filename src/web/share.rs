@@ -0,0 +1,119 @@
+use stdweb::web::window;
+
+use rs2048::replay::Replay;
+use rs2048::save::Position;
+use rs2048::Game;
+
+/// Start of the URL fragment holding a shared position
+const PREFIX: &str = "#pos=";
+
+/// Start of the URL fragment holding a shared replay
+const REPLAY_PREFIX: &str = "#replay=";
+
+/// The page's URL without its fragment
+fn base_url() -> Option<String> {
+    let href = window().location()?.href().ok()?;
+    Some(href.split('#').next().unwrap_or_default().to_owned())
+}
+
+/// Load the position shared in the URL fragment (`#pos=...`), if any
+///
+/// The fragment is removed afterwards, so reloading the page resumes the
+/// game instead of starting over from the shared position. The sharer's
+/// score, moves and best score are dropped, only what is played from the
+/// position on counts for the high scores and statistics.
+#[allow(clippy::unused_unit)]
+pub fn load_position() -> Option<Game> {
+    let hash = window().location()?.hash().ok()?;
+    let encoded = hash.strip_prefix(PREFIX)?;
+    if let Some(url) = base_url() {
+        let _ = window().history().replace_state((), "", Some(&url));
+    }
+    let position = Position::from_base64(encoded).map(|position| Position {
+        score: 0,
+        best: 0,
+        moves: 0,
+        ..position
+    });
+    match position.and_then(Game::from_position) {
+        Ok(game) => Some(game),
+        Err(error) => {
+            let message = format!("Failed to load shared position: {}", error);
+            console!(log, message);
+            None
+        }
+    }
+}
+
+/// Load the replay shared in the URL fragment (`#replay=...`), if any
+///
+/// Like with positions, the fragment is removed afterwards.
+#[allow(clippy::unused_unit)]
+pub fn load_replay() -> Option<Replay> {
+    let hash = window().location()?.hash().ok()?;
+    let encoded = hash.strip_prefix(REPLAY_PREFIX)?;
+    if let Some(url) = base_url() {
+        let _ = window().history().replace_state((), "", Some(&url));
+    }
+    match Replay::from_base64(encoded) {
+        Ok(replay) => Some(replay),
+        Err(error) => {
+            let message = format!("Failed to load shared replay: {}", error);
+            console!(log, message);
+            None
+        }
+    }
+}
+
+/// A link that opens the app at `game`'s current position
+pub fn share_url(game: &Game) -> Option<String> {
+    Some(format!(
+        "{}{}{}",
+        base_url()?,
+        PREFIX,
+        game.position().to_base64()
+    ))
+}
+
+/// A link that opens the app playing back `replay`
+pub fn replay_url(replay: &Replay) -> Option<String> {
+    Some(format!(
+        "{}{}{}",
+        base_url()?,
+        REPLAY_PREFIX,
+        replay.to_base64()
+    ))
+}
+
+/// Copy `url` to the clipboard
+///
+/// Falls back to showing the link in a prompt where the clipboard API
+/// isn't available.
+fn copy_url(url: String, what: &str) {
+    let message = format!("Copy this link to share the {}:", what);
+    js! { @(no_return)
+        var url = @{url};
+        var message = @{message};
+        if (navigator.clipboard) {
+            navigator.clipboard.writeText(url).catch(function() {
+                window.prompt(message, url);
+            });
+        } else {
+            window.prompt(message, url);
+        }
+    }
+}
+
+/// Copy a link to `game`'s current position to the clipboard
+pub fn copy_share_url(game: &Game) {
+    if let Some(url) = share_url(game) {
+        copy_url(url, "position");
+    }
+}
+
+/// Copy a link playing back `replay` to the clipboard
+pub fn copy_replay_url(replay: &Replay) {
+    if let Some(url) = replay_url(replay) {
+        copy_url(url, "replay");
+    }
+}
@@ -0,0 +1,212 @@
+// The `console!` logging below goes through stdweb's `js!` macro, whose
+// expansion ends in a `()` clippy reports as unused
+#![allow(clippy::unused_unit)]
+
+use stdweb::web::window;
+
+use rs2048::replay::{Player, Recorder, Replay};
+use rs2048::save::SavedGame;
+use rs2048::scores::HighScores;
+use rs2048::stats::Stats;
+use rs2048::theme::{self, Theme};
+use rs2048::Game;
+
+/// Key of the best score on boards of a shape, 4x4 keeps the key it had
+/// before other shapes could be played
+fn best_key(width: usize, height: usize) -> String {
+    match (width, height) {
+        (4, 4) => "best".to_owned(),
+        _ => format!("best-{}x{}", width, height),
+    }
+}
+
+/// Recover the best score on boards of a shape stored before there was a
+/// high score table
+pub fn load_best(width: usize, height: usize) -> Option<usize> {
+    window()
+        .local_storage()
+        .get(&best_key(width, height))?
+        .parse::<usize>()
+        .ok()
+}
+
+/// Resume the game saved in local storage
+///
+/// Saves that can't be loaded are dropped, so a broken save doesn't keep
+/// the app from starting.
+pub fn load_game() -> Option<Game> {
+    let json = window().local_storage().get("game")?;
+    match SavedGame::from_json(&json).and_then(Game::load) {
+        Ok(game) => Some(game),
+        Err(error) => {
+            let message = format!("Failed to load saved game: {}", error);
+            console!(log, message);
+            window().local_storage().remove("game");
+            None
+        }
+    }
+}
+
+/// Store the current game in local storage
+pub fn save_game(game: &Game) {
+    if window()
+        .local_storage()
+        .insert("game", &game.save().to_json())
+        .is_err()
+    {
+        console!(log, "Failed to save game to local storage!");
+    }
+}
+
+/// Continue recording the replay saved in local storage
+///
+/// The replay is only used if playing it through ends up at `game`, so a
+/// replay left over from another game isn't extended with this one.
+pub fn load_replay(game: &Game) -> Option<Recorder> {
+    let encoded = window().local_storage().get("replay")?;
+    let replay = match Replay::from_base64(&encoded) {
+        Ok(replay) => replay,
+        Err(error) => {
+            let message = format!("Failed to load saved replay: {}", error);
+            console!(log, message);
+            window().local_storage().remove("replay");
+            return None;
+        }
+    };
+    let mut player = Player::new(replay.clone()).ok()?;
+    player.seek(player.len());
+    let end = player.get_game();
+    if end.get_states() != game.get_states() || end.get_score() != game.get_score() {
+        return None;
+    }
+    Some(Recorder::resume(replay))
+}
+
+/// Store the replay of the current game in local storage
+pub fn save_replay(recorder: &Recorder) {
+    if window()
+        .local_storage()
+        .insert("replay", &recorder.get_replay().to_base64())
+        .is_err()
+    {
+        console!(log, "Failed to save replay to local storage!");
+    }
+}
+
+/// Recover the high score table from local storage
+///
+/// A table that can't be loaded is replaced by an empty one.
+pub fn load_scores() -> HighScores {
+    let json = match window().local_storage().get("scores") {
+        Some(json) => json,
+        None => return HighScores::default(),
+    };
+    HighScores::from_json(&json).unwrap_or_else(|error| {
+        let message = format!("Failed to load high scores: {}", error);
+        console!(log, message);
+        HighScores::default()
+    })
+}
+
+/// Store the high score table in local storage
+pub fn save_scores(scores: &HighScores) {
+    if window()
+        .local_storage()
+        .insert("scores", &scores.to_json())
+        .is_err()
+    {
+        console!(log, "Failed to save high scores to local storage!");
+    }
+}
+
+/// The built-in themes followed by the custom ones in local storage
+pub fn load_themes() -> Vec<Theme> {
+    let mut themes = Theme::builtin();
+    let json = match window().local_storage().get("themes") {
+        Some(json) => json,
+        None => return themes,
+    };
+    let custom: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap_or_default();
+    for value in custom {
+        match Theme::from_json(&value.to_string()) {
+            Ok(custom) => theme::add_theme(&mut themes, custom),
+            Err(error) => {
+                let message = format!("Failed to load theme: {}", error);
+                console!(log, message);
+            }
+        }
+    }
+    themes
+}
+
+/// Keep a custom theme in local storage so it can be picked again
+pub fn save_theme(custom: &Theme) {
+    let builtin = Theme::builtin();
+    let mut themes: Vec<Theme> = load_themes()
+        .into_iter()
+        .filter(|theme| !builtin.contains(theme))
+        .collect();
+    theme::add_theme(&mut themes, custom.clone());
+    let json = serde_json::to_string(&themes).expect("themes are always valid JSON");
+    if window().local_storage().insert("themes", &json).is_err() {
+        console!(log, "Failed to save theme to local storage!");
+    }
+}
+
+/// Name of the theme picked last time
+pub fn load_theme_name() -> Option<String> {
+    window().local_storage().get("theme")
+}
+
+/// Remember the theme picked for next time
+pub fn save_theme_name(name: &str) {
+    if window().local_storage().insert("theme", name).is_err() {
+        console!(log, "Failed to save theme choice to local storage!");
+    }
+}
+
+/// Seconds spent playing the saved game
+pub fn load_time() -> u64 {
+    window()
+        .local_storage()
+        .get("time")
+        .and_then(|time| time.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Store the seconds spent playing the current game
+pub fn save_time(seconds: u64) {
+    if window()
+        .local_storage()
+        .insert("time", &seconds.to_string())
+        .is_err()
+    {
+        console!(log, "Failed to save play time to local storage!");
+    }
+}
+
+/// Recover the statistics from local storage
+///
+/// Statistics that can't be loaded start over.
+pub fn load_stats() -> Stats {
+    let json = match window().local_storage().get("stats") {
+        Some(json) => json,
+        None => return Stats::default(),
+    };
+    Stats::from_json(&json).unwrap_or_else(|error| {
+        let message = format!("Failed to load statistics: {}", error);
+        console!(log, message);
+        Stats::default()
+    })
+}
+
+/// Store the statistics in local storage
+pub fn save_stats(stats: &Stats) {
+    if window()
+        .local_storage()
+        .insert("stats", &stats.to_json())
+        .is_err()
+    {
+        console!(log, "Failed to save statistics to local storage!");
+    }
+}
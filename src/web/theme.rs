@@ -0,0 +1,67 @@
+use stdweb::traits::*;
+use stdweb::unstable::TryInto;
+use stdweb::web::html_element::SelectElement;
+use stdweb::web::{alert, document};
+
+use rs2048::theme::Theme;
+
+/// Value of the picker's entry for loading a custom theme
+pub const LOAD_CUSTOM: &str = "";
+
+/// The theme picker
+pub fn picker() -> SelectElement {
+    document()
+        .get_element_by_id("theme")
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// List `themes` in the picker with the one called `selected` picked,
+/// followed by an entry for loading a custom theme
+pub fn fill_picker(themes: &[Theme], selected: &str) {
+    let picker = picker();
+    while let Some(child) = picker.first_child() {
+        let _ = picker.remove_child(&child);
+    }
+    for theme in themes {
+        let option = document().create_element("option").unwrap();
+        let _ = option.set_attribute("value", &theme.name);
+        option.set_text_content(&theme.name);
+        picker.append_child(&option);
+    }
+    let option = document().create_element("option").unwrap();
+    let _ = option.set_attribute("value", LOAD_CUSTOM);
+    option.set_text_content("Load custom theme\u{2026}");
+    picker.append_child(&option);
+    let _ = picker.set_value(Some(selected));
+}
+
+/// Colour the page around the board in `theme`
+///
+/// The stylesheet reads these from CSS variables on the root element.
+pub fn style_page(theme: &Theme) {
+    js! { @(no_return)
+        var style = document.documentElement.style;
+        style.setProperty("--page", @{&theme.page});
+        style.setProperty("--text", @{&theme.text});
+        style.setProperty("--board", @{&theme.board});
+        style.setProperty("--board-text", @{&theme.board_text});
+    }
+}
+
+/// Ask for the JSON of a custom theme, `None` if the user cancels or it
+/// can't be loaded
+pub fn ask_custom() -> Option<Theme> {
+    let json = js! {
+        return prompt("Paste the JSON of a theme, e.g. {\"name\": \"mine\", \"board\": \"#303050\"}");
+    }
+    .into_string()?;
+    match Theme::from_json(&json) {
+        Ok(theme) => Some(theme),
+        Err(error) => {
+            alert(&format!("Failed to load theme: {}", error));
+            None
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use stdweb::unstable::TryInto;
+use stdweb::web::event;
+
+use rs2048::theme::Theme;
+use rs2048::Direction;
+
+use crate::web::canvas::OverlayAction;
+
+pub enum GameEvent {
+    KeyDown(event::KeyDownEvent),
+    /// A mouse button, pen or finger went down on the board at a point
+    /// and time in milliseconds
+    SwipeStart(Point, f64),
+    /// It was lifted again
+    SwipeEnd(Point, f64),
+    /// The browser took over the gesture, e.g. for a second finger
+    SwipeCancel,
+    Frame(f64),
+    Tick,
+    /// A board shape (width, height) was picked
+    Shape(usize, usize),
+    /// The window was resized or moved to a display with another density
+    Resize,
+    /// A theme was picked
+    Theme(Box<Theme>),
+    /// A strategy for autoplay was picked by name
+    Strategy(String),
+    /// A hint was asked for
+    Hint,
+    /// A button on the game-over overlay was tapped, or its key pressed
+    Action(OverlayAction),
+    Exit,
+}
+
+pub struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    pub fn from_data(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    pub fn get_x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> f64 {
+        self.y
+    }
+
+    fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// Time spent playing a game in seconds
+///
+/// It runs from a move until the game ends and keeps the time played
+/// before the page was reloaded.
+#[derive(Default)]
+pub struct Stopwatch {
+    elapsed: u64,
+    since: Option<u64>,
+}
+
+impl Stopwatch {
+    /// A stopped stopwatch that already counted `elapsed` seconds
+    pub fn new(elapsed: u64) -> Self {
+        Stopwatch {
+            elapsed,
+            since: None,
+        }
+    }
+
+    /// Start counting at `now` unless it already is
+    pub fn start(&mut self, now: u64) {
+        self.since.get_or_insert(now);
+    }
+
+    pub fn stop(&mut self, now: u64) {
+        self.elapsed = self.elapsed(now);
+        self.since = None;
+    }
+
+    /// Seconds counted up to `now`
+    pub fn elapsed(&self, now: u64) -> u64 {
+        self.elapsed + self.since.map_or(0, |since| now.saturating_sub(since))
+    }
+}
+
+/// Turns the start and end of a swipe into a move
+///
+/// Mouse, pointer and touch events all report the same gesture, whichever
+/// ends it first makes the move and the others are ignored.
+pub struct Swipe {
+    start: Option<(Point, f64)>,
+    min_distance: f64,
+    min_velocity: f64,
+}
+
+impl Swipe {
+    /// Swipes need to cover `min_distance` pixels at `min_velocity` pixels
+    /// per millisecond, so taps and slow drags don't move the tiles
+    pub fn new(min_distance: f64, min_velocity: f64) -> Self {
+        Swipe {
+            start: None,
+            min_distance,
+            min_velocity,
+        }
+    }
+
+    pub fn begin(&mut self, point: Point, time: f64) {
+        self.start = Some((point, time));
+    }
+
+    pub fn cancel(&mut self) {
+        self.start = None;
+    }
+
+    /// The direction of the swipe ending at `point`, if it was one
+    pub fn end(&mut self, point: Point, time: f64) -> Option<Direction> {
+        let (start, start_time) = self.start.take()?;
+        let distance = start.distance(&point);
+        let velocity = distance / (time - start_time).max(1.0);
+        if distance < self.min_distance || velocity < self.min_velocity {
+            return None;
+        }
+        Some(get_direction(&start, &point))
+    }
+}
+
+pub fn get_direction(last: &Point, current: &Point) -> Direction {
+    if (last.x - current.x).abs() > (last.y - current.y).abs() {
+        // move horizontal
+        if last.x > current.x {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    } else {
+        // move vertical
+        if last.y > current.y {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+/// The current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    let millis: f64 = js! { return Date.now(); }.try_into().unwrap();
+    (millis / 1000.0) as u64
+}
+
+/// Milliseconds since the page was loaded, for timing the AI
+pub fn clock() -> f64 {
+    js! { return performance.now(); }.try_into().unwrap()
+}
+
+/// Draw a fresh seed for a new game from the browser's `Math.random()`
+pub fn get_seed() -> u64 {
+    let rand = js! { return Math.random(); };
+    let base: f64 = rand.try_into().unwrap();
+    (base * 9_007_199_254_740_992.0).floor() as u64
+}